@@ -0,0 +1,221 @@
+use crate::{get_authenticated_user, get_cookie, Aborted, Args};
+use colored::*;
+use reqwest::Client;
+use serde::Deserialize;
+use std::{
+    collections::hash_map::RandomState,
+    fs,
+    hash::{BuildHasher, Hasher},
+    path::Path,
+};
+
+#[derive(Deserialize)]
+struct CurrencyResponse {
+    robux: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupEconomyPermissions {
+    spend_group_funds: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupPermissions {
+    group_economy_permissions: GroupEconomyPermissions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupMembershipResponse {
+    permissions: Option<GroupPermissions>,
+}
+
+/// Empty unless the account has a warning or ban to acknowledge
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotApprovedResponse {
+    punishment_type_description: Option<String>,
+    message_to_user: Option<String>,
+    end_date: Option<String>,
+}
+
+pub async fn get_robux_balance(
+    client: &Client,
+    args: &Args,
+    user_id: u64,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let currency = client
+        .get(format!(
+            "{}/v1/users/{}/currency",
+            args.economy_base_url, user_id
+        ))
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?
+        .json::<CurrencyResponse>()
+        .await?;
+
+    Ok(currency.robux)
+}
+
+async fn has_premium(
+    client: &Client,
+    args: &Args,
+    user_id: u64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let premium = client
+        .get(format!(
+            "{}/v1/users/{}/validate-membership",
+            args.premium_features_base_url, user_id
+        ))
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?
+        .json::<bool>()
+        .await?;
+
+    Ok(premium)
+}
+
+/// Fails unless the authenticated user may spend the funds of the group
+pub async fn validate_group_funds(
+    client: &Client,
+    args: &Args,
+    group_id: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = client
+        .get(format!(
+            "{}/v1/groups/{}/membership",
+            args.groups_base_url, group_id
+        ))
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Group {} can't be used for purchases (status {})",
+            group_id,
+            response.status().as_u16()
+        )
+        .into());
+    }
+
+    let membership = response.json::<GroupMembershipResponse>().await?;
+
+    if !membership
+        .permissions
+        .is_some_and(|permissions| permissions.group_economy_permissions.spend_group_funds)
+    {
+        return Err(format!("Not allowed to spend the funds of group {}", group_id).into());
+    }
+
+    Ok(())
+}
+
+/// Stops the run when the account has a pending warning or is disabled,
+/// since every purchase fails until that is resolved on the website
+pub async fn check_account_state(
+    client: &Client,
+    args: &Args,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = client
+        .get(format!("{}/v1/not-approved", args.usermoderation_base_url))
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        println!(
+            "{} Couldn't check the state of the account (status {})",
+            "Warning".bold().yellow(),
+            response.status().as_u16()
+        );
+        return Ok(());
+    }
+
+    let response = response.json::<NotApprovedResponse>().await?;
+    let Some(punishment) = response.punishment_type_description else {
+        return Ok(());
+    };
+
+    let mut reason = format!(
+        "The account has a pending moderation action ({})",
+        punishment
+    );
+    if let Some(message) = response
+        .message_to_user
+        .filter(|message| !message.is_empty())
+    {
+        reason.push_str(&format!(": {}", message));
+    }
+    if let Some(end_date) = response.end_date {
+        reason.push_str(&format!(", until {}", end_date));
+    }
+    reason.push_str(". Log in on the website to resolve it before running again");
+
+    Err(Aborted(reason).into())
+}
+
+/// Warns when the balance can't cover --max-spend, so a paid run doesn't stop
+/// halfway on insufficient funds
+pub fn warn_if_balance_is_short(args: &Args, robux: u64) {
+    if let Some(max_spend) = args.max_spend {
+        if robux < max_spend {
+            println!(
+                "{} The balance of {} Robux is below --max-spend of {} Robux",
+                "Warning".bold().yellow(),
+                robux,
+                max_spend
+            );
+        }
+    }
+}
+
+pub async fn print_whoami(client: &Client, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let user = get_authenticated_user(client, args).await?;
+    let robux = get_robux_balance(client, args, user.id).await?;
+    let premium = has_premium(client, args, user.id).await?;
+
+    println!(
+        "{} (@{}, id {})",
+        user.display_name.bold(),
+        user.name,
+        user.id
+    );
+    println!("  Robux: {}", robux.to_string().bold().blue());
+    println!("  Premium: {}", if premium { "yes" } else { "no" });
+
+    warn_if_balance_is_short(args, robux);
+
+    Ok(())
+}
+
+/// Reads one cookie per line, skipping blank lines and `#` comments
+pub fn load_auth_file(path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+
+    let accounts: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_start_matches(".ROBLOSECURITY=").to_string())
+        .collect();
+
+    if accounts.is_empty() {
+        return Err(format!("No accounts in {}", path.display()).into());
+    }
+
+    Ok(accounts)
+}
+
+/// Fisher-Yates shuffle seeded by the randomly keyed std hasher
+pub fn shuffle<T>(items: &mut [T]) {
+    for index in (1..items.len()).rev() {
+        let random = RandomState::new().build_hasher().finish();
+        items.swap(index, (random % (index as u64 + 1)) as usize);
+    }
+}
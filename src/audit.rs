@@ -0,0 +1,75 @@
+use crate::{authenticated_user_owns_bundle, get_search_page, Session};
+use colored::*;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+pub struct AuditSummary {
+    pub owned: usize,
+    pub unowned: usize,
+}
+
+/// Checks the ownership of every item of the search without purchasing,
+/// writing the ids of unowned items to `unowned_path` one per line
+pub async fn audit_catalog(
+    session: &Session,
+    unowned_path: Option<&Path>,
+) -> Result<AuditSummary, Box<dyn std::error::Error>> {
+    let mut writer = match unowned_path {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+    let mut next_page_cursor: Option<String> = None;
+    let mut summary = AuditSummary {
+        owned: 0,
+        unowned: 0,
+    };
+
+    loop {
+        let response = get_search_page(
+            &session.client,
+            &session.args,
+            &session.timings,
+            &next_page_cursor,
+        )
+        .await
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
+
+        for asset in response.data.unwrap_or_default().iter() {
+            if authenticated_user_owns_bundle(session, asset).await? {
+                summary.owned += 1;
+                continue;
+            }
+
+            summary.unowned += 1;
+
+            if session.args.verbose {
+                println!("{} is not owned", asset.name.truecolor(150, 150, 150));
+            }
+
+            if let Some(writer) = &mut writer {
+                writeln!(writer, "{}", asset.id)?;
+            }
+        }
+
+        if let Some(writer) = &mut writer {
+            writer.flush()?;
+        }
+
+        println!(
+            "{}",
+            format!("Checked {} items so far", summary.owned + summary.unowned)
+                .truecolor(150, 150, 150)
+        );
+
+        if response.next_page_cursor.is_none() {
+            break;
+        }
+
+        next_page_cursor = response.next_page_cursor;
+    }
+
+    Ok(summary)
+}
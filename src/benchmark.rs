@@ -0,0 +1,110 @@
+use crate::{
+    get_authenticated_user, get_cookie, get_search_page, get_search_url, Args, Timings,
+    SEARCH_PAGE_SIZE,
+};
+use colored::*;
+use reqwest::{Client, StatusCode};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Request rates tried in turn, in requests per minute
+const BENCHMARK_RATES: &[u64] = &[30, 60, 120, 240, 480, 960, 1920];
+/// Requests sent at each rate, alternating searches and ownership checks
+const REQUESTS_PER_RATE: u64 = 20;
+
+/// Sends search and ownership requests at increasing rates until one is
+/// ratelimited and reports the highest rate that wasn't. Nothing is purchased
+pub async fn run_benchmark(
+    client: &Client,
+    args: &Args,
+    timings: &Arc<Timings>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let user_id = get_authenticated_user(client, args).await?.id;
+    let items = get_search_page(client, args, timings, &None)
+        .await
+        .map_err(|error| error as Box<dyn std::error::Error>)?
+        .data
+        .unwrap_or_default();
+
+    if items.is_empty() {
+        return Err("The search returned no items to check the ownership of".into());
+    }
+
+    let mut sustained_rate: Option<u64> = None;
+
+    for &rate in BENCHMARK_RATES {
+        let interval = Duration::from_secs(60) / rate as u32;
+        let started_at = Instant::now();
+        let mut ratelimited: u64 = 0;
+
+        for index in 0..REQUESTS_PER_RATE {
+            let request_started_at = Instant::now();
+            let request = if index % 2 == 0 {
+                client.get(get_search_url(args, &None, SEARCH_PAGE_SIZE))
+            } else {
+                let item = &items[(index as usize / 2) % items.len()];
+                client
+                    .get(format!(
+                        "{}/v1/users/{}/items/{}/{}/is-owned",
+                        args.inventory_base_url, user_id, item.item_type, item.id
+                    ))
+                    .header("Cookie", get_cookie(args))
+            };
+
+            if request.send().await?.status() == StatusCode::TOO_MANY_REQUESTS {
+                ratelimited += 1;
+            }
+
+            tokio::time::sleep(interval.saturating_sub(request_started_at.elapsed())).await;
+        }
+
+        let achieved_rate = (REQUESTS_PER_RATE as f64 * 60.0
+            / started_at.elapsed().as_secs_f64().max(f64::EPSILON))
+        .round();
+
+        println!(
+            "  {} requests/min: {} of {} ratelimited, achieved {} requests/min",
+            rate.to_string().bold().blue(),
+            if ratelimited > 0 {
+                ratelimited.to_string().bold().red()
+            } else {
+                ratelimited.to_string().bold().green()
+            },
+            REQUESTS_PER_RATE,
+            achieved_rate
+        );
+
+        if ratelimited > 0 {
+            break;
+        }
+
+        sustained_rate = Some(rate);
+
+        // Latency caps the rate before the ratelimit does, going faster is pointless
+        if achieved_rate < rate as f64 * 0.9 {
+            println!(
+                "{}",
+                "Requests take longer than the interval, stopping here".truecolor(150, 150, 150)
+            );
+            break;
+        }
+    }
+
+    match sustained_rate {
+        Some(rate) => println!(
+            "{} Sustained {} requests/min without ratelimits, recommended: at most one request every {}ms",
+            "Done".bold().green(),
+            rate.to_string().bold().blue(),
+            60_000 / rate
+        ),
+        None => println!(
+            "{} Ratelimited even at {} requests/min, wait a while before running again",
+            "Warning".bold().yellow(),
+            BENCHMARK_RATES[0]
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use reqwest::Response;
+
+/// Reads a response body up to `limit` bytes. Bodies announced or found to be
+/// larger are refused instead of being buffered whole
+pub async fn read_body(mut response: Response, limit: u64) -> Result<Vec<u8>, String> {
+    let too_large = || format!("body is larger than the --max-body-size of {} bytes", limit);
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|error| format!("body was cut off after {} bytes: {}", body.len(), error))?
+    {
+        if (body.len() + chunk.len()) as u64 > limit {
+            return Err(too_large());
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+/// A size in bytes like `4096`, `512K` or `16M`, in powers of 1024
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let number_end = value
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(number_end);
+
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| format!("{:?} isn't a size", value))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().trim_end_matches(['b', 'i']) {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        _ => return Err(format!("unknown unit in {:?}, use K, M or G", value)),
+    };
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("{:?} is too large", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_read_with_binary_units() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("16MiB"), Ok(16 * 1024 * 1024));
+        assert_eq!(parse_size("1 gb"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("12 parsecs").is_err());
+        assert!(parse_size("M").is_err());
+    }
+}
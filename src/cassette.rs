@@ -0,0 +1,194 @@
+use crate::mock_server::{read_request, write_response, MockResponse, MockServer};
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+
+// Response headers worth keeping, anything else (cookies above all) is dropped
+const RECORDED_HEADERS: &[&str] = &["x-csrf-token", "retry-after"];
+
+/// One request of a recorded run and the response Roblox gave to it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Interaction {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    /// JSON bodies are kept as is to stay readable, anything else as a string
+    pub body: Value,
+}
+
+impl Interaction {
+    fn to_response(&self) -> MockResponse {
+        let body = match &self.body {
+            Value::String(body) => body.clone(),
+            body => body.to_string(),
+        };
+
+        self.headers.iter().fold(
+            MockResponse::json(self.status, &body),
+            |response, (name, value)| response.with_header(name, value),
+        )
+    }
+}
+
+/// Requests and responses of a real run, replayed in order to test the
+/// whole purchase flow against the shapes Roblox actually sends
+#[derive(Serialize, Deserialize, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+fn cassette_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cassettes")
+        .join(format!("{}.json", name))
+}
+
+impl Cassette {
+    pub fn load(name: &str) -> Cassette {
+        let path = cassette_path(name);
+        let contents = fs::read_to_string(&path)
+            .unwrap_or_else(|error| panic!("Failed to read {}: {}", path.display(), error));
+
+        serde_json::from_str(&contents)
+            .unwrap_or_else(|error| panic!("Malformed cassette {}: {}", path.display(), error))
+    }
+
+    pub fn save(&self, name: &str) {
+        let path = cassette_path(name);
+        fs::write(&path, serde_json::to_string_pretty(self).unwrap() + "\n").unwrap();
+    }
+
+    /// Serves the recorded responses of each endpoint in the order they
+    /// were recorded. The query string is left out of the matching, so a
+    /// page is answered by the next recorded response of its endpoint
+    pub async fn replay(&self) -> MockServer {
+        let mut routes: Vec<(String, String, Vec<MockResponse>)> = Vec::new();
+
+        for interaction in &self.interactions {
+            let path = interaction
+                .path
+                .split('?')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            let response = interaction.to_response();
+
+            match routes.iter_mut().find(|(method, route_path, _)| {
+                *method == interaction.method && *route_path == path
+            }) {
+                Some((_, _, responses)) => responses.push(response),
+                None => routes.push((interaction.method.clone(), path, vec![response])),
+            }
+        }
+
+        // Routes match by prefix, the longest paths have to be tried first
+        routes.sort_by_key(|(_, path, _)| std::cmp::Reverse(path.len()));
+
+        MockServer::start(
+            routes
+                .iter()
+                .map(|(method, path, responses)| {
+                    (method.as_str(), path.as_str(), responses.clone())
+                })
+                .collect(),
+        )
+        .await
+    }
+}
+
+/// Forwards requests to Roblox and records each of them with its response.
+/// The cookie is only ever forwarded and response cookies are dropped, so
+/// the cassette can be shared
+#[derive(Default)]
+pub struct Recorder {
+    interactions: Arc<Mutex<Vec<Interaction>>>,
+}
+
+impl Recorder {
+    /// Starts a proxy for one of the Roblox domains, returning its base URL
+    /// to pass in place of the real one
+    pub async fn proxy(&self, upstream: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let upstream = upstream.trim_end_matches('/').to_string();
+        let interactions = self.interactions.clone();
+        let client = Client::new();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let Some(request) = read_request(&mut stream).await else {
+                    continue;
+                };
+
+                let mut forwarded = client.request(
+                    Method::from_bytes(request.method.as_bytes()).unwrap(),
+                    format!("{}{}", upstream, request.path),
+                );
+                for (name, value) in &request.headers {
+                    // Compressed bodies couldn't be read back to record them
+                    if !matches!(
+                        name.as_str(),
+                        "host" | "content-length" | "connection" | "accept-encoding"
+                    ) {
+                        forwarded = forwarded.header(name, value);
+                    }
+                }
+
+                let response = match forwarded.body(request.body).send().await {
+                    Ok(response) => response,
+                    Err(error) => {
+                        let body = format!(r#"{{"errors":[{{"code":0,"message":"{}"}}]}}"#, error);
+                        write_response(&mut stream, &MockResponse::json(502, &body)).await;
+                        continue;
+                    }
+                };
+
+                let status = response.status().as_u16();
+                let headers: Vec<(String, String)> = RECORDED_HEADERS
+                    .iter()
+                    .filter_map(|name| {
+                        let value = response.headers().get(*name)?.to_str().ok()?;
+                        Some((name.to_string(), value.to_string()))
+                    })
+                    .collect();
+                let body = response.text().await.unwrap_or_default();
+
+                let interaction = Interaction {
+                    method: request.method,
+                    path: request.path,
+                    status,
+                    headers,
+                    body: serde_json::from_str(&body).unwrap_or(Value::String(body)),
+                };
+                write_response(&mut stream, &interaction.to_response()).await;
+                interactions.lock().unwrap().push(interaction);
+            }
+        });
+
+        base_url
+    }
+
+    /// The recorded interactions, with the CSRF token of the session replaced
+    /// since it is tied to the cookie
+    pub fn finish(&self) -> Cassette {
+        let mut interactions = self.interactions.lock().unwrap().clone();
+
+        for interaction in interactions.iter_mut() {
+            for (name, value) in interaction.headers.iter_mut() {
+                if name == "x-csrf-token" {
+                    *value = "csrf-token".to_string();
+                }
+            }
+        }
+
+        Cassette { interactions }
+    }
+}
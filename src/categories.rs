@@ -0,0 +1,194 @@
+use crate::{is_numeric_id, Args};
+use colored::*;
+
+/// Category names accepted by the catalog search
+const CATEGORIES: &[&str] = &[
+    "All",
+    "Featured",
+    "Collectibles",
+    "Clothing",
+    "BodyParts",
+    "Gear",
+    "Accessories",
+    "AvatarAnimations",
+    "CommunityCreations",
+];
+
+/// Subcategory names accepted by the catalog search
+const SUBCATEGORIES: &[&str] = &[
+    "Accessories",
+    "AllCollectibles",
+    "AnimationBundles",
+    "BackAccessories",
+    "Bodies",
+    "BodyParts",
+    "Bottoms",
+    "Bundles",
+    "ClassicPants",
+    "ClassicShirts",
+    "ClassicTShirts",
+    "Clothing",
+    "CommunityCreations",
+    "DressSkirts",
+    "EmoteAnimations",
+    "FaceAccessories",
+    "Faces",
+    "Featured",
+    "FrontAccessories",
+    "Gear",
+    "HairAccessories",
+    "Hats",
+    "Heads",
+    "Jackets",
+    "NeckAccessories",
+    "Pants",
+    "Shirts",
+    "Shoes",
+    "Shorts",
+    "ShoulderAccessories",
+    "Sweaters",
+    "TShirts",
+    "Tops",
+    "WaistAccessories",
+];
+
+// Largest edit distance at which a typo is still corrected
+const MAX_TYPO_DISTANCE: usize = 2;
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle
+        .chars()
+        .all(|character| haystack.any(|other| other == character))
+}
+
+fn levenshtein(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    let mut previous: Vec<usize> = (0..=second.len()).collect();
+
+    for (index, first_character) in first.chars().enumerate() {
+        let mut current = vec![index + 1];
+
+        for (other_index, second_character) in second.iter().enumerate() {
+            let substitution =
+                previous[other_index] + usize::from(first_character != *second_character);
+            current.push(
+                substitution
+                    .min(previous[other_index + 1] + 1)
+                    .min(current[other_index] + 1),
+            );
+        }
+
+        previous = current;
+    }
+
+    previous[second.len()]
+}
+
+/// Resolves a partial or misspelled name to one of `names`. Unknown names
+/// are kept as is since the search may know categories missing from the list
+fn resolve_name(value: &str, names: &[&'static str]) -> Result<Option<&'static str>, String> {
+    let lowercase = value.to_lowercase();
+
+    if let Some(name) = names.iter().find(|name| name.to_lowercase() == lowercase) {
+        return Ok(Some(name));
+    }
+
+    let matches: Vec<&'static str> = names
+        .iter()
+        .copied()
+        .filter(|name| is_subsequence(&lowercase, &name.to_lowercase()))
+        .collect();
+
+    let matches = if matches.is_empty() {
+        let closest = names
+            .iter()
+            .map(|name| (levenshtein(&lowercase, &name.to_lowercase()), *name))
+            .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+            .min_by_key(|(distance, _)| *distance);
+
+        match closest {
+            Some((distance, _)) => names
+                .iter()
+                .copied()
+                .filter(|name| levenshtein(&lowercase, &name.to_lowercase()) == distance)
+                .collect(),
+            None => Vec::new(),
+        }
+    } else {
+        matches
+    };
+
+    match matches.as_slice() {
+        [] => Ok(None),
+        [name] => Ok(Some(name)),
+        // A prefix of exactly one name wins over names merely containing it
+        _ => match matches
+            .iter()
+            .filter(|name| name.to_lowercase().starts_with(&lowercase))
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [name] => Ok(Some(name)),
+            _ => Err(format!(
+                "{:?} is ambiguous, did you mean one of {}?",
+                value,
+                matches.join(", ")
+            )),
+        },
+    }
+}
+
+fn resolve(
+    flag: &str,
+    value: &mut Option<String>,
+    names: &[&'static str],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(current) = value.as_deref().filter(|current| !is_numeric_id(current)) else {
+        return Ok(());
+    };
+
+    match resolve_name(current, names).map_err(|error| format!("--{} {}", flag, error))? {
+        Some(name) if name != current => {
+            println!(
+                "{}",
+                format!("Resolved --{} {} to {}", flag, current, name).truecolor(150, 150, 150)
+            );
+            *value = Some(name.to_string());
+        }
+        Some(_) => {}
+        None => println!(
+            "{} --{} {} is not a known name, searching with it as is",
+            "Warning".bold().yellow(),
+            flag,
+            current
+        ),
+    }
+
+    Ok(())
+}
+
+/// Replaces partial or misspelled category and subcategory names with the
+/// catalog's own, e.g. `accesso` with `Accessories`
+pub fn resolve_categories(args: &mut Args) -> Result<(), Box<dyn std::error::Error>> {
+    resolve("category", &mut args.category, CATEGORIES)?;
+    resolve("subcategory", &mut args.subcategory, SUBCATEGORIES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_and_misspelled_names_are_resolved() {
+        assert_eq!(resolve_name("accesso", CATEGORIES), Ok(Some("Accessories")));
+        assert_eq!(
+            resolve_name("ACCESSORIES", CATEGORIES),
+            Ok(Some("Accessories"))
+        );
+        assert_eq!(resolve_name("Clohting", CATEGORIES), Ok(Some("Clothing")));
+        assert_eq!(resolve_name("hat", SUBCATEGORIES), Ok(Some("Hats")));
+        assert_eq!(resolve_name("Something", CATEGORIES), Ok(None));
+        assert!(resolve_name("sh", SUBCATEGORIES).is_err());
+    }
+}
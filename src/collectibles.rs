@@ -0,0 +1,279 @@
+use crate::{
+    add_bound_auth_token, get_cookie, ApiError, Args, MarketplaceQueryResponseItem, PurchaseApi,
+    RATELIMIT_ERROR_CODE,
+};
+use reqwest::{Body, Client, Error, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectibleDetailsQuery {
+    item_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectibleDetails {
+    collectible_item_id: String,
+    collectible_product_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectiblePurchaseQuery {
+    pub collectible_item_id: String,
+    pub collectible_product_id: String,
+    pub expected_currency: u8,
+    pub expected_price: u32,
+    pub expected_purchaser_id: String,
+    pub expected_purchaser_type: String,
+    pub expected_seller_id: u64,
+    pub expected_seller_type: String,
+    pub idempotency_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collectible_item_instance_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectiblePurchaseResponse {
+    purchased: bool,
+    error_message: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PurchaseAmount {
+    currency_code: &'static str,
+    amount: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PurchaseParty {
+    id: String,
+    #[serde(rename = "type")]
+    party_type: String,
+}
+
+/// The v2 purchase request, which nests the price, purchaser and seller
+/// that v1 spreads over flat fields
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectiblePurchaseQueryV2 {
+    collectible_item_id: String,
+    collectible_product_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collectible_item_instance_id: Option<String>,
+    expected_price: PurchaseAmount,
+    purchaser: PurchaseParty,
+    seller: PurchaseParty,
+    idempotency_key: String,
+}
+
+impl From<CollectiblePurchaseQuery> for CollectiblePurchaseQueryV2 {
+    fn from(query: CollectiblePurchaseQuery) -> Self {
+        CollectiblePurchaseQueryV2 {
+            collectible_item_id: query.collectible_item_id,
+            collectible_product_id: query.collectible_product_id,
+            collectible_item_instance_id: query.collectible_item_instance_id,
+            expected_price: PurchaseAmount {
+                currency_code: "Robux",
+                amount: query.expected_price,
+            },
+            purchaser: PurchaseParty {
+                id: query.expected_purchaser_id,
+                party_type: query.expected_purchaser_type,
+            },
+            seller: PurchaseParty {
+                id: query.expected_seller_id.to_string(),
+                party_type: query.expected_seller_type,
+            },
+            idempotency_key: query.idempotency_key,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PurchaseFailure {
+    code: Option<u32>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectiblePurchaseResponseV2 {
+    status: String,
+    failure: Option<PurchaseFailure>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResellersResponse {
+    data: Vec<Resale>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResaleSeller {
+    pub seller_id: u64,
+    pub seller_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resale {
+    pub collectible_product_id: String,
+    pub collectible_item_instance_id: String,
+    pub seller: ResaleSeller,
+    pub price: u32,
+}
+
+impl From<CollectiblePurchaseQuery> for Body {
+    fn from(query: CollectiblePurchaseQuery) -> Body {
+        let json_string =
+            serde_json::to_string(&query).expect("Failed to serialize CollectiblePurchaseQuery");
+        Body::from(json_string)
+    }
+}
+
+pub async fn get_collectible_product_id(
+    client: &Client,
+    args: &Args,
+    csrf_token: &str,
+    collectible_item_id: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let details = client
+        .post(format!(
+            "{}/marketplace-items/v1/items/details",
+            args.apis_base_url
+        ))
+        .json(&CollectibleDetailsQuery {
+            item_ids: vec![collectible_item_id.to_string()],
+        })
+        .header("Cookie", get_cookie(args))
+        .header("X-CSRF-TOKEN", csrf_token)
+        .send()
+        .await?
+        .json::<Vec<CollectibleDetails>>()
+        .await?;
+
+    details
+        .into_iter()
+        .find(|details| details.collectible_item_id == collectible_item_id)
+        .map(|details| details.collectible_product_id)
+        .ok_or_else(|| format!("No collectible details for {}", collectible_item_id).into())
+}
+
+pub async fn get_cheapest_resale(
+    client: &Client,
+    args: &Args,
+    collectible_item_id: &str,
+) -> Result<Option<Resale>, Box<dyn std::error::Error>> {
+    let resellers = client
+        .get(format!(
+            "{}/marketplace-sales/v1/item/{}/resellers?limit=10",
+            args.apis_base_url, collectible_item_id
+        ))
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?
+        .json::<ResellersResponse>()
+        .await?;
+
+    Ok(resellers.data.into_iter().min_by_key(|resale| resale.price))
+}
+
+/// Buys a collectible through the API chosen with --purchase-api. Both only
+/// sell collectibles, other items always go through the economy endpoint
+pub async fn purchase_collectible(
+    client: &Client,
+    asset: &MarketplaceQueryResponseItem,
+    args: &Args,
+    csrf_token: &str,
+    query: CollectiblePurchaseQuery,
+) -> Result<Response, Error> {
+    let request = match args.purchase_api {
+        PurchaseApi::Legacy => client
+            .post(format!(
+                "{}/marketplace-sales/v1/item/{}/purchase-item",
+                args.apis_base_url,
+                asset.collectible_item_id.as_deref().unwrap_or_default()
+            ))
+            .body(query),
+        PurchaseApi::V2 => client
+            .post(format!(
+                "{}/marketplace-sales/v2/purchase",
+                args.apis_base_url
+            ))
+            .json(&CollectiblePurchaseQueryV2::from(query)),
+    };
+
+    add_bound_auth_token(request, args)
+        .header("Content-Type", "application/json; charset=utf-8")
+        .header("Cookie", get_cookie(args))
+        .header("X-CSRF-TOKEN", csrf_token)
+        .send()
+        .await
+}
+
+/// Reads a collectible purchase response into the same error shape the
+/// economy endpoint uses, so both routes share the retry logic
+pub async fn get_collectible_purchase_errors(
+    response: Response,
+    args: &Args,
+) -> Result<Option<Vec<ApiError>>, Error> {
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Ok(Some(vec![ApiError {
+            code: RATELIMIT_ERROR_CODE,
+            message: None,
+        }]));
+    }
+
+    if args.purchase_api == PurchaseApi::V2 {
+        let body = response.json::<CollectiblePurchaseResponseV2>().await?;
+
+        if body.status == "Succeeded" {
+            return Ok(None);
+        }
+
+        let failure = body.failure.unwrap_or(PurchaseFailure {
+            code: None,
+            message: None,
+        });
+        return Ok(Some(vec![ApiError {
+            code: failure.code.unwrap_or(0),
+            message: failure.message.or(Some(body.status)),
+        }]));
+    }
+
+    let body = response.json::<CollectiblePurchaseResponse>().await?;
+
+    if body.purchased {
+        return Ok(None);
+    }
+
+    Ok(Some(vec![ApiError {
+        code: 0,
+        message: body.error_message,
+    }]))
+}
+
+pub fn generate_idempotency_key() -> String {
+    let high = RandomState::new().build_hasher().finish();
+    let low = RandomState::new().build_hasher().finish();
+
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0x0fff,
+        ((low >> 48) & 0x3fff) | 0x8000,
+        low & 0xffff_ffff_ffff
+    )
+}
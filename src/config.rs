@@ -0,0 +1,254 @@
+use crate::{
+    events::EventKind, Args, CreatorType, CustomHeader, Format, OwnedCheckErrorAction, PurchaseApi,
+    PurchaseOrder, Shard, Source, Timestamp,
+};
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, Parser};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Defaults for command line options, loaded from a JSON file. Keys are the
+/// long option names, e.g. `"max-per-creator": 3`. Options passed on the
+/// command line always take precedence.
+pub struct Config {
+    path: PathBuf,
+    values: Map<String, Value>,
+}
+
+/// The options a config may set. Only used to reject unknown keys and values
+/// of the wrong type up front, the values themselves go through the same
+/// parsers as the command line
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+#[allow(dead_code)]
+struct ConfigOptions {
+    category: Option<String>,
+    subcategory: Option<String>,
+    source: Option<Source>,
+    universe_id: Option<u64>,
+    favorites: Option<u32>,
+    ids_file: Option<PathBuf>,
+    creator_store: Option<u64>,
+    creator_store_type: Option<CreatorType>,
+    max_page_size: Option<usize>,
+    min_page_size: Option<usize>,
+    randomize_accounts: Option<bool>,
+    prefetch_pages: Option<usize>,
+    max_concurrent_pages: Option<u64>,
+    reverse: Option<bool>,
+    max_buffered_pages: Option<u64>,
+    max_price: Option<u32>,
+    include_not_for_sale: Option<bool>,
+    max_spend: Option<u64>,
+    max_spend_rate: Option<u64>,
+    price_tolerance: Option<u32>,
+    use_group_funds: Option<u64>,
+    max_per_creator: Option<u32>,
+    creator_cooldown_ms: Option<u64>,
+    #[serde(alias = "prioritize")]
+    purchase_order: Option<PurchaseOrder>,
+    min_favorites: Option<u64>,
+    max_favorites: Option<u64>,
+    watch: Option<bool>,
+    shard: Option<Shard>,
+    start_at: Option<Timestamp>,
+    allow_resale_up_to: Option<u32>,
+    purchase_timeout_secs: Option<u64>,
+    total_retries: Option<u32>,
+    defer_ratelimited: Option<bool>,
+    max_404_streak: Option<u32>,
+    fail_fast: Option<bool>,
+    retry_codes: Option<Vec<u32>>,
+    skip_codes: Option<Vec<u32>>,
+    abort_codes: Option<Vec<u32>>,
+    skip_failed_pages: Option<bool>,
+    failures: Option<PathBuf>,
+    receipts: Option<PathBuf>,
+    skip_completed_subcategories: Option<bool>,
+    confirm_purchases: Option<bool>,
+    no_owned_check: Option<bool>,
+    on_owned_check_error: Option<OwnedCheckErrorAction>,
+    reconcile_ownership: Option<bool>,
+    throttle_on_403: Option<bool>,
+    throttle_min_ms: Option<u64>,
+    throttle_max_ms: Option<u64>,
+    warm_up_steps: Option<u32>,
+    warm_up_ms: Option<u64>,
+    on_purchase: Option<String>,
+    #[serde(rename = "header")]
+    headers: Option<Vec<CustomHeader>>,
+    creator_types: Option<Vec<CreatorType>>,
+    sample_rate: Option<f64>,
+    seed: Option<u64>,
+    locale: Option<String>,
+    http1_only: Option<bool>,
+    rate: Option<u32>,
+    escalate_cooldown_after: Option<u32>,
+    max_cooldown: Option<u64>,
+    ownership_concurrency: Option<u32>,
+    max_body_size: Option<u64>,
+    network_retries: Option<u32>,
+    poll_interval: Option<u64>,
+    seen_bloom: Option<bool>,
+    seen_bloom_capacity: Option<u64>,
+    seen_bloom_fp_rate: Option<f64>,
+    resume: Option<bool>,
+    state_dir: Option<PathBuf>,
+    event_log: Option<PathBuf>,
+    skip_reported: Option<bool>,
+    manifest: Option<PathBuf>,
+    format: Option<Format>,
+    purchase_api: Option<PurchaseApi>,
+    verbose: Option<bool>,
+    quiet: Option<bool>,
+    show: Option<Vec<EventKind>>,
+    timings: Option<bool>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read config {}: {}", path.display(), error))?;
+        let parse_error = |error: serde_json::Error| {
+            format!("Failed to parse config {}: {}", path.display(), error)
+        };
+
+        let values: Map<String, Value> = serde_json::from_str(&contents).map_err(parse_error)?;
+        serde_json::from_value::<ConfigOptions>(Value::Object(values.clone()))
+            .map_err(parse_error)?;
+
+        Ok(Config {
+            path: path.to_path_buf(),
+            values,
+        })
+    }
+
+    /// Parses the command line again with the options of the config put in
+    /// front of it, so they are checked exactly like options given there.
+    /// Options set on the command line or in the environment are left out
+    pub fn apply(
+        self,
+        command_line: &[OsString],
+        matches: &ArgMatches,
+    ) -> Result<Args, Box<dyn std::error::Error>> {
+        let command = Args::command();
+        let (program, command_line) = command_line
+            .split_first()
+            .ok_or("The command line is empty")?;
+        let mut argv = vec![program.clone()];
+
+        for (key, value) in self.values {
+            let Some(arg) = command.get_arguments().find(|arg| {
+                arg.get_long() == Some(key.as_str())
+                    || arg
+                        .get_all_aliases()
+                        .is_some_and(|aliases| aliases.contains(&key.as_str()))
+            }) else {
+                continue;
+            };
+
+            if is_explicit(matches, arg.get_id().as_str()) {
+                continue;
+            }
+
+            let flag = OsString::from(format!("--{}", arg.get_long().unwrap_or(&key)));
+
+            match value {
+                Value::Null | Value::Bool(false) => {}
+                Value::Bool(true) => argv.push(flag),
+                Value::Array(values) => {
+                    for value in values {
+                        argv.extend([flag.clone(), to_arg(value)]);
+                    }
+                }
+                value => argv.extend([flag, to_arg(value)]),
+            }
+        }
+
+        argv.extend(command_line.iter().cloned());
+
+        Args::try_parse_from(argv).map_err(|error| {
+            let error = error.to_string();
+            let error = error.lines().next().unwrap_or_default();
+
+            format!(
+                "Invalid config {}: {}",
+                self.path.display(),
+                error.trim_start_matches("error: ")
+            )
+            .into()
+        })
+    }
+}
+
+fn to_arg(value: Value) -> OsString {
+    match value {
+        Value::String(value) => value.into(),
+        value => value.to_string().into(),
+    }
+}
+
+fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_with_config(name: &str, contents: &str, extra: &[&str]) -> Result<Args, String> {
+        let path =
+            std::env::temp_dir().join(format!("fugc-config-{}-{}.json", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+
+        let mut command_line: Vec<OsString> =
+            vec!["fugc".into(), "--config".into(), (&path).into()];
+        command_line.extend(extra.iter().map(OsString::from));
+        let matches = Args::command().get_matches_from(&command_line);
+        let args = Config::load(&path)
+            .and_then(|config| config.apply(&command_line, &matches))
+            .map_err(|error| error.to_string());
+        fs::remove_file(&path).unwrap();
+
+        args
+    }
+
+    #[test]
+    fn config_values_are_parsed_like_the_command_line() {
+        let args = parse_with_config(
+            "valid",
+            r#"{"locale": "EN-US", "max-price": 5, "prioritize": "newest", "retry-codes": [27, 7]}"#,
+            &["--max-price", "3"],
+        )
+        .unwrap_or_else(|error| panic!("{}", error));
+
+        assert_eq!(args.locale.as_deref(), Some("en-us"));
+        assert_eq!(args.max_price, 3);
+        assert!(args.purchase_order == PurchaseOrder::Newest);
+        assert_eq!(args.retry_codes, vec![27, 7]);
+    }
+
+    #[test]
+    fn out_of_range_config_values_are_rejected() {
+        for (name, contents) in [
+            ("rate", r#"{"rate": 0}"#),
+            ("page-size", r#"{"max-page-size": 0}"#),
+            ("sample-rate", r#"{"sample-rate": 2.5}"#),
+            ("requires", r#"{"reconcile-ownership": true}"#),
+            ("conflicts", r#"{"favorites": 8, "ids-file": "ids.txt"}"#),
+        ] {
+            let Err(error) = parse_with_config(name, contents, &[]) else {
+                panic!("{} was accepted", contents);
+            };
+
+            assert!(error.starts_with("Invalid config"), "{}", error);
+        }
+    }
+}
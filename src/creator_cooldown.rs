@@ -0,0 +1,84 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Last purchase from each creator for --creator-cooldown-ms, so items of a
+/// creator still cooling down can wait behind those of other creators
+pub struct CreatorCooldowns {
+    cooldown: Duration,
+    last_purchases: HashMap<u64, Instant>,
+}
+
+impl CreatorCooldowns {
+    pub fn new(cooldown: Duration) -> Self {
+        CreatorCooldowns {
+            cooldown,
+            last_purchases: HashMap::new(),
+        }
+    }
+
+    pub fn record_purchase(&mut self, creator_id: u64, now: Instant) {
+        self.last_purchases.insert(creator_id, now);
+    }
+
+    fn ready_at(&self, creator_id: u64) -> Option<Instant> {
+        self.last_purchases
+            .get(&creator_id)
+            .map(|last_purchase| *last_purchase + self.cooldown)
+    }
+
+    /// Index of the first item that can be handled at `now`. `creator_of`
+    /// gives the creator of items about to be purchased, and `None` for
+    /// items that won't be. When every creator is cooling down, the item
+    /// ready the soonest is returned along with when it will be
+    pub fn pick<T>(
+        &self,
+        queue: &VecDeque<T>,
+        creator_of: impl Fn(&T) -> Option<u64>,
+        now: Instant,
+    ) -> (usize, Option<Instant>) {
+        let ready_at = |item: &T| creator_of(item).and_then(|creator_id| self.ready_at(creator_id));
+
+        if let Some(index) = queue
+            .iter()
+            .position(|item| ready_at(item).is_none_or(|ready_at| ready_at <= now))
+        {
+            return (index, None);
+        }
+
+        queue
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| Some((index, ready_at(item)?)))
+            .min_by_key(|(_, ready_at)| *ready_at)
+            .map(|(index, ready_at)| (index, Some(ready_at)))
+            .unwrap_or((0, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_of_cooling_creators_wait_behind_other_creators() {
+        let now = Instant::now();
+        let mut cooldowns = CreatorCooldowns::new(Duration::from_secs(5));
+        cooldowns.record_purchase(1, now);
+        cooldowns.record_purchase(2, now - Duration::from_secs(2));
+
+        let queue: VecDeque<Option<u64>> = VecDeque::from([Some(1), Some(2), None, Some(3)]);
+        assert_eq!(cooldowns.pick(&queue, |creator| *creator, now), (2, None));
+
+        let cooling: VecDeque<Option<u64>> = VecDeque::from([Some(1), Some(2)]);
+        assert_eq!(
+            cooldowns.pick(&cooling, |creator| *creator, now),
+            (1, Some(now + Duration::from_secs(3)))
+        );
+        assert_eq!(
+            cooldowns.pick(&cooling, |creator| *creator, now + Duration::from_secs(5)),
+            (0, None)
+        );
+    }
+}
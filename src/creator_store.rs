@@ -0,0 +1,87 @@
+use crate::{
+    add_locale, get_item_details, CreatorType, ItemDetailsQueryItem, MarketplaceQueryResponse,
+    Session,
+};
+use serde::Deserialize;
+
+const STORE_PAGE_SIZE: usize = 120;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreItem {
+    id: u64,
+    item_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreResponse {
+    next_page_cursor: Option<String>,
+    #[serde(default)]
+    data: Vec<StoreItem>,
+}
+
+/// Reads a page of the items a user or group published, as listed on their
+/// store, in the shape of a search page. Unlike the search this isn't tied to
+/// a category, so every item of the creator comes up
+pub async fn get_store_page(
+    session: &Session,
+    creator_id: u64,
+    creator_type: CreatorType,
+    next_page_cursor: &Option<String>,
+) -> Result<MarketplaceQueryResponse, Box<dyn std::error::Error>> {
+    let request = session.client.get(format!(
+        "{}/v1/search/items?category=All&creatorTargetId={}&creatorType={}&limit={}&cursor={}",
+        session.args.catalog_base_url,
+        creator_id,
+        creator_type.get_api_name(),
+        STORE_PAGE_SIZE,
+        next_page_cursor.as_deref().unwrap_or_default()
+    ));
+    let response = add_locale(request, &session.args).send().await?;
+    let status = response.status().as_u16();
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to read the store of {} {}, status {}",
+            creator_type.get_api_name().to_lowercase(),
+            creator_id,
+            status
+        )
+        .into());
+    }
+
+    let store = response.json::<StoreResponse>().await?;
+
+    let mut items = if store.data.is_empty() {
+        Vec::new()
+    } else {
+        get_item_details(
+            session,
+            store
+                .data
+                .into_iter()
+                .map(|item| ItemDetailsQueryItem {
+                    item_type: item.item_type,
+                    id: item.id,
+                })
+                .collect(),
+        )
+        .await?
+    };
+
+    // The store lists items whatever their price, including those off sale
+    items.retain(|item| {
+        item.price
+            .is_some_and(|price| price <= session.args.max_price)
+    });
+
+    Ok(MarketplaceQueryResponse {
+        next_page_cursor: store.next_page_cursor,
+        data: Some(items),
+        errors: None,
+        total_results: None,
+        status,
+        page_size: STORE_PAGE_SIZE,
+    })
+}
@@ -0,0 +1,92 @@
+use crate::MarketplaceQueryResponseItem;
+use std::{cmp::Ordering, collections::BinaryHeap, time::Instant};
+
+/// A ratelimited purchase to try again once its cooldown passed
+pub struct PendingRetry {
+    pub item: MarketplaceQueryResponseItem,
+    pub ready_at: Instant,
+}
+
+// Reversed so the max-heap of std yields the earliest `ready_at` first
+impl Ord for PendingRetry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.ready_at.cmp(&self.ready_at)
+    }
+}
+
+impl PartialOrd for PendingRetry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PendingRetry {
+    fn eq(&self, other: &Self) -> bool {
+        self.ready_at == other.ready_at
+    }
+}
+
+impl Eq for PendingRetry {}
+
+/// Ratelimited purchases waiting for their cooldown while the run goes on
+/// with other items, with --defer-ratelimited
+#[derive(Default)]
+pub struct DeferredRetries {
+    pending: BinaryHeap<PendingRetry>,
+}
+
+impl DeferredRetries {
+    pub fn push(&mut self, item: MarketplaceQueryResponseItem, ready_at: Instant) {
+        self.pending.push(PendingRetry { item, ready_at });
+    }
+
+    /// Takes the earliest retry if its cooldown passed by `now`
+    pub fn pop_ready(&mut self, now: Instant) -> Option<MarketplaceQueryResponseItem> {
+        if self.pending.peek()?.ready_at > now {
+            return None;
+        }
+
+        self.pending.pop().map(|retry| retry.item)
+    }
+
+    pub fn next_ready_at(&self) -> Option<Instant> {
+        self.pending.peek().map(|retry| retry.ready_at)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn item(id: u64) -> MarketplaceQueryResponseItem {
+        serde_json::from_str(&format!(
+            r#"{{"id":{},"itemType":"Asset","name":"Hat","productId":1,"creatorType":"User","creatorTargetId":1}}"#,
+            id
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn retries_come_out_in_cooldown_order_once_ready() {
+        let now = Instant::now();
+        let mut retries = DeferredRetries::default();
+
+        retries.push(item(1), now + Duration::from_secs(30));
+        retries.push(item(2), now);
+        retries.push(item(3), now + Duration::from_secs(10));
+
+        assert_eq!(retries.pop_ready(now).map(|item| item.id), Some(2));
+        assert!(retries.pop_ready(now).is_none());
+        assert_eq!(retries.next_ready_at(), Some(now + Duration::from_secs(10)));
+
+        let later = now + Duration::from_secs(60);
+        assert_eq!(retries.pop_ready(later).map(|item| item.id), Some(3));
+        assert_eq!(retries.pop_ready(later).map(|item| item.id), Some(1));
+        assert_eq!(retries.len(), 0);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    get_authenticated_user, get_csrf_token, get_search_page, is_asset_available, Args,
+    Availability, Session, Timings,
+};
+use colored::*;
+use reqwest::Client;
+use std::sync::Arc;
+
+struct Checks {
+    failed: u32,
+}
+
+impl Checks {
+    fn pass(&self, check: &str, details: String) {
+        println!("{} {} ({})", "PASS".bold().green(), check, details);
+    }
+
+    fn fail(&mut self, check: &str, error: String, hint: &str) {
+        self.failed += 1;
+        println!("{} {} ({})", "FAIL".bold().red(), check, error);
+        println!("     {}", hint.truecolor(150, 150, 150));
+    }
+
+    fn skip(&self, check: &str, reason: &str) {
+        println!(
+            "{} {} ({})",
+            "SKIP".bold().yellow(),
+            check,
+            reason.truecolor(150, 150, 150)
+        );
+    }
+}
+
+/// Runs every step of a purchase run except the purchase itself, reporting
+/// each as passed or failed with a hint on how to fix it
+pub async fn run_checks(
+    client: &Client,
+    args: &Args,
+    timings: &Arc<Timings>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut checks = Checks { failed: 0 };
+
+    let user_id = match get_authenticated_user(client, args).await {
+        Ok(user) => {
+            checks.pass(
+                "Cookie",
+                format!("signed in as {} ({})", user.name, user.id),
+            );
+            Some(user.id)
+        }
+        Err(error) => {
+            checks.fail(
+                "Cookie",
+                error.to_string(),
+                "Copy a fresh .ROBLOSECURITY cookie from a signed in browser into --auth",
+            );
+            None
+        }
+    };
+
+    let csrf_token = match get_csrf_token(client, args).await {
+        Ok(csrf_token) => {
+            checks.pass("CSRF token", "fetched".to_string());
+            Some(csrf_token)
+        }
+        Err(error) => {
+            checks.fail(
+                "CSRF token",
+                error.to_string(),
+                "Check the cookie and your connection, or pass a token with --csrf-token",
+            );
+            None
+        }
+    };
+
+    let first_item = match get_search_page(client, args, timings, &None).await {
+        Ok(page) => {
+            let items = page.data.unwrap_or_default();
+            checks.pass(
+                "Catalog search",
+                format!("{} items on the first page", items.len()),
+            );
+            items.into_iter().next()
+        }
+        Err(error) => {
+            checks.fail(
+                "Catalog search",
+                error.to_string(),
+                "Check --category and --subcategory, or try again if the catalog is down",
+            );
+            None
+        }
+    };
+
+    match (user_id, csrf_token, first_item) {
+        (Some(user_id), Some(csrf_token), Some(item)) => {
+            let session = Session::new(
+                client.clone(),
+                args.clone(),
+                timings.clone(),
+                user_id,
+                csrf_token,
+            );
+
+            match is_asset_available(&session, &item).await {
+                Ok(availability) => checks.pass(
+                    "Availability check",
+                    format!(
+                        "{} is {}",
+                        item.name,
+                        match availability {
+                            Availability::Available => "available",
+                            Availability::Owned => "owned",
+                            Availability::CreatedByRoblox => "created by Roblox",
+                            Availability::OwnCreation => "your own creation",
+                            Availability::Unknown => "of unknown ownership",
+                        }
+                    ),
+                ),
+                Err(error) => checks.fail(
+                    "Availability check",
+                    error.to_string(),
+                    "The inventory API refused the request, check that the cookie is valid",
+                ),
+            }
+        }
+        _ => checks.skip("Availability check", "needs the checks above to pass"),
+    }
+
+    if checks.failed > 0 {
+        return Err(format!("{} checks failed", checks.failed).into());
+    }
+
+    println!("{} Everything looks fine", "Done".bold().green());
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use crate::{get_search_page, Args, Timings};
+use reqwest::Client;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Writes every item of the search to `path` as a JSON array, one page at a
+/// time so the whole catalog never has to be held in memory
+pub async fn dump_catalog(
+    client: &Client,
+    args: &Args,
+    timings: &Timings,
+    path: &Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut next_page_cursor: Option<String> = None;
+    let mut dumped_items: usize = 0;
+
+    writer.write_all(b"[")?;
+
+    loop {
+        let response = get_search_page(client, args, timings, &next_page_cursor)
+            .await
+            .map_err(|error| error as Box<dyn std::error::Error>)?;
+
+        for asset in response.data.unwrap_or_default().iter() {
+            if dumped_items > 0 {
+                writer.write_all(b",")?;
+            }
+
+            writer.write_all(b"\n  ")?;
+            serde_json::to_writer(&mut writer, asset)?;
+            dumped_items += 1;
+        }
+
+        writer.flush()?;
+
+        if response.next_page_cursor.is_none() {
+            break;
+        }
+
+        next_page_cursor = response.next_page_cursor;
+    }
+
+    writer.write_all(b"\n]\n")?;
+    writer.flush()?;
+
+    Ok(dumped_items)
+}
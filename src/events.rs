@@ -0,0 +1,258 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PurchaseEvent<'a> {
+    /// Marks which account the events that follow belong to
+    Started {
+        user_id: u64,
+    },
+    Purchased {
+        id: u64,
+        name: &'a str,
+        price: u32,
+    },
+    Skipped {
+        id: u64,
+        name: &'a str,
+    },
+    SaleEnded {
+        id: u64,
+        name: &'a str,
+    },
+    AlreadyOwned {
+        id: u64,
+        name: &'a str,
+    },
+    NotFound {
+        id: u64,
+        name: &'a str,
+    },
+    Moderated {
+        id: u64,
+        name: &'a str,
+    },
+    Failed {
+        id: u64,
+        name: &'a str,
+    },
+    Ratelimited {
+        waited_ms: u64,
+    },
+    Finished {
+        purchased: u32,
+        skipped: u32,
+        sale_ended: u32,
+        failed: u32,
+        spent: u64,
+    },
+}
+
+/// Kinds of item events that can be picked with --show
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    Purchased,
+    Skipped,
+    SaleEnded,
+    AlreadyOwned,
+    NotFound,
+    Moderated,
+    Failed,
+    Ratelimited,
+}
+
+impl PurchaseEvent<'_> {
+    /// `None` for the events marking the start and end of a run, which are
+    /// always kept
+    pub fn kind(&self) -> Option<EventKind> {
+        match self {
+            PurchaseEvent::Started { .. } | PurchaseEvent::Finished { .. } => None,
+            PurchaseEvent::Purchased { .. } => Some(EventKind::Purchased),
+            PurchaseEvent::Skipped { .. } => Some(EventKind::Skipped),
+            PurchaseEvent::SaleEnded { .. } => Some(EventKind::SaleEnded),
+            PurchaseEvent::AlreadyOwned { .. } => Some(EventKind::AlreadyOwned),
+            PurchaseEvent::NotFound { .. } => Some(EventKind::NotFound),
+            PurchaseEvent::Moderated { .. } => Some(EventKind::Moderated),
+            PurchaseEvent::Failed { .. } => Some(EventKind::Failed),
+            PurchaseEvent::Ratelimited { .. } => Some(EventKind::Ratelimited),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    // Milliseconds since the Unix epoch
+    time: u128,
+    #[serde(flatten)]
+    event: &'a PurchaseEvent<'a>,
+}
+
+/// Appends every event to a file as one JSON object per line. Each line is
+/// flushed as it's written, events are few next to the requests behind them
+/// and none should be lost to a crash or a kill during a quiet stretch
+pub struct EventLog {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl EventLog {
+    pub fn create(path: &Path) -> Result<EventLog, Box<dyn std::error::Error>> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| format!("Failed to open event log {}: {}", path.display(), error))?;
+
+        Ok(EventLog {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn write(&self, event: &PurchaseEvent) -> io::Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut writer = self.writer.lock().unwrap();
+
+        serde_json::to_writer(&mut *writer, &EventRecord { time, event })?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+/// Ids of the items an event log records as purchased by `user_id`, along
+/// with the number of purchases recorded for other accounts. A missing log
+/// has no purchases
+pub fn load_purchased_ids(
+    path: &Path,
+    user_id: u64,
+) -> Result<(HashSet<u64>, usize), Box<dyn std::error::Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok((HashSet::new(), 0));
+        }
+        Err(error) => {
+            return Err(format!("Failed to read event log {}: {}", path.display(), error).into())
+        }
+    };
+    let mut purchased_ids = HashSet::new();
+    let mut other_accounts: usize = 0;
+    let mut current_user_id: Option<u64> = None;
+
+    for line in BufReader::new(file).lines() {
+        // A crash can leave a partly written last line behind
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(&line?) else {
+            continue;
+        };
+
+        match event["event"].as_str() {
+            Some("started") => current_user_id = event["user_id"].as_u64(),
+            Some("purchased") => match event["id"].as_u64() {
+                Some(id) if current_user_id == Some(user_id) => {
+                    purchased_ids.insert(id);
+                }
+                _ => other_accounts += 1,
+            },
+            _ => {}
+        }
+    }
+
+    Ok((purchased_ids, other_accounts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_written_as_one_json_object_per_line() {
+        let path = std::env::temp_dir().join(format!("fugc-events-{}.ndjson", std::process::id()));
+        let event_log = EventLog::create(&path).unwrap();
+
+        event_log
+            .write(&PurchaseEvent::Purchased {
+                id: 14476435962,
+                name: "Free Hat",
+                price: 0,
+            })
+            .unwrap();
+        event_log
+            .write(&PurchaseEvent::Ratelimited { waited_ms: 65000 })
+            .unwrap();
+        event_log.flush().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "purchased");
+        assert_eq!(lines[0]["id"], 14476435962u64);
+        assert_eq!(lines[1]["event"], "ratelimited");
+        assert!(lines[1]["time"].is_u64());
+    }
+
+    #[test]
+    fn purchased_ids_are_loaded_for_the_same_account_only() {
+        let path =
+            std::env::temp_dir().join(format!("fugc-reported-{}.ndjson", std::process::id()));
+        let event_log = EventLog::create(&path).unwrap();
+
+        for event in [
+            PurchaseEvent::Purchased {
+                id: 1,
+                name: "Before any start",
+                price: 0,
+            },
+            PurchaseEvent::Started { user_id: 1234 },
+            PurchaseEvent::Purchased {
+                id: 2,
+                name: "Hat",
+                price: 0,
+            },
+            PurchaseEvent::Skipped {
+                id: 3,
+                name: "Skipped",
+            },
+            PurchaseEvent::Started { user_id: 5678 },
+            PurchaseEvent::Purchased {
+                id: 4,
+                name: "Other account",
+                price: 0,
+            },
+            PurchaseEvent::Started { user_id: 1234 },
+            PurchaseEvent::Purchased {
+                id: 5,
+                name: "Shirt",
+                price: 0,
+            },
+        ] {
+            event_log.write(&event).unwrap();
+        }
+        event_log.flush().unwrap();
+
+        let (purchased_ids, other_accounts) = load_purchased_ids(&path, 1234).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(purchased_ids, HashSet::from([2, 5]));
+        assert_eq!(other_accounts, 2);
+    }
+}
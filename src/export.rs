@@ -0,0 +1,108 @@
+use crate::{get_cookie, sanitize_name, Session};
+use colored::*;
+use serde::Deserialize;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+const INVENTORY_PAGE_SIZE: usize = 100;
+
+/// Asset types UGC items are published as, accessories and layered clothing
+const UGC_ASSET_TYPES: &[&str] = &[
+    "Hat",
+    "HairAccessory",
+    "FaceAccessory",
+    "NeckAccessory",
+    "ShoulderAccessory",
+    "FrontAccessory",
+    "BackAccessory",
+    "WaistAccessory",
+    "TShirtAccessory",
+    "ShirtAccessory",
+    "PantsAccessory",
+    "JacketAccessory",
+    "SweaterAccessory",
+    "ShortsAccessory",
+    "LeftShoeAccessory",
+    "RightShoeAccessory",
+    "DressSkirtAccessory",
+];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InventoryAsset {
+    asset_id: u64,
+    #[serde(default)]
+    asset_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InventoryResponse {
+    next_page_cursor: Option<String>,
+    #[serde(default)]
+    data: Vec<InventoryAsset>,
+}
+
+/// Writes every UGC item the account owns to `path` as `id<TAB>name` lines,
+/// a page of the inventory at a time
+pub async fn export_owned(
+    session: &Session,
+    path: &Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut next_page_cursor: Option<String> = None;
+    let mut exported_items: usize = 0;
+
+    loop {
+        let started_at = Instant::now();
+        let response = session
+            .client
+            .get(format!(
+                "{}/v2/users/{}/inventory?assetTypes={}&limit={}&sortOrder=Asc&cursor={}",
+                session.args.inventory_base_url,
+                session.user_id,
+                UGC_ASSET_TYPES.join(","),
+                INVENTORY_PAGE_SIZE,
+                next_page_cursor.as_deref().unwrap_or_default()
+            ))
+            .header("Cookie", get_cookie(&session.args))
+            .send()
+            .await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(format!("Failed to read the inventory, status {}", status).into());
+        }
+
+        let response = response.json::<InventoryResponse>().await?;
+        session.timings.record("Inventory", started_at);
+
+        for asset in response.data.iter() {
+            writeln!(
+                writer,
+                "{}\t{}",
+                asset.asset_id,
+                sanitize_name(&asset.asset_name)
+            )?;
+        }
+
+        writer.flush()?;
+        exported_items += response.data.len();
+
+        println!(
+            "{}",
+            format!("Exported {} items so far", exported_items).truecolor(150, 150, 150)
+        );
+
+        match response.next_page_cursor {
+            Some(cursor) if !cursor.is_empty() => next_page_cursor = Some(cursor),
+            _ => break,
+        }
+    }
+
+    Ok(exported_items)
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+/// Reasons of failures that trying again can't fix
+const PERMANENT_FAILURES: &[&str] = &[
+    "owned",
+    "not for sale",
+    "off sale",
+    "moderated",
+    "no longer exists",
+];
+
+/// Appends the items that failed to purchase to a file, one per line as the
+/// item id and the reason separated by a tab
+pub struct FailuresFile {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl FailuresFile {
+    pub fn create(path: &Path) -> Result<FailuresFile, Box<dyn std::error::Error>> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| {
+                format!("Failed to open failures file {}: {}", path.display(), error)
+            })?;
+
+        Ok(FailuresFile {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Failures are rare, so each one is written out right away
+    pub fn record(&self, id: u64, reason: &str) -> std::io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+
+        // The reason is free text, it must stay on its line
+        writeln!(
+            writer,
+            "{}\t{}",
+            id,
+            reason.replace(['\t', '\n', '\r'], " ")
+        )?;
+        writer.flush()
+    }
+}
+
+/// Reads the ids of a failures file worth trying again, in the order they
+/// failed, along with the number of ids whose failure is permanent
+pub fn load_retryable_ids(path: &Path) -> Result<(Vec<u64>, usize), Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read failures file {}: {}", path.display(), error))?;
+
+    let mut ids = Vec::new();
+    let mut permanent = HashSet::new();
+
+    for line in contents.lines() {
+        let (id, reason) = line.split_once('\t').unwrap_or((line, ""));
+        let Ok(id) = id.trim().parse::<u64>() else {
+            continue;
+        };
+        let reason = reason.to_lowercase();
+
+        if PERMANENT_FAILURES
+            .iter()
+            .any(|permanent_reason| reason.contains(permanent_reason))
+        {
+            permanent.insert(id);
+        } else if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    ids.retain(|id| !permanent.contains(id));
+
+    Ok((ids, permanent.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_are_written_as_id_and_reason_lines() {
+        let path = std::env::temp_dir().join(format!("fugc-failures-{}.tsv", std::process::id()));
+        let failures = FailuresFile::create(&path).unwrap();
+
+        failures.record(14476435962, "purchase failed").unwrap();
+        failures
+            .record(1, "error sending request\n\tcaused by: timeout")
+            .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            contents,
+            "14476435962\tpurchase failed\n1\terror sending request  caused by: timeout\n"
+        );
+    }
+
+    #[test]
+    fn permanent_failures_are_not_retried() {
+        let path = std::env::temp_dir().join(format!("fugc-retry-{}.tsv", std::process::id()));
+        std::fs::write(
+            &path,
+            "1\tpurchase failed\n2\tItem is not for sale\n1\ttimed out after 15s\nbad\tline\n3\tpurchase failed\n3\tAlready owned\n",
+        )
+        .unwrap();
+
+        let retryable = load_retryable_ids(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(retryable, (vec![1], 2));
+    }
+}
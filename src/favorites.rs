@@ -0,0 +1,74 @@
+use crate::{
+    get_cookie, get_item_details, ItemDetailsQueryItem, MarketplaceQueryResponse, Session,
+};
+use serde::Deserialize;
+
+const FAVORITES_PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+struct FavoriteAsset {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FavoritesResponse {
+    next_page_cursor: Option<String>,
+    data: Vec<FavoriteAsset>,
+}
+
+/// Reads a page of the authenticated user's favorites of one asset type in
+/// the shape of a search page, with the details the search would include
+pub async fn get_favorites_page(
+    session: &Session,
+    asset_type_id: u32,
+    next_page_cursor: &Option<String>,
+) -> Result<MarketplaceQueryResponse, Box<dyn std::error::Error>> {
+    let response = session
+        .client
+        .get(format!(
+            "{}/v1/favorites/users/{}/favorites/{}/assets?limit={}&cursor={}",
+            session.args.catalog_base_url,
+            session.user_id,
+            asset_type_id,
+            FAVORITES_PAGE_SIZE,
+            next_page_cursor.as_deref().unwrap_or_default()
+        ))
+        .header("Cookie", get_cookie(&session.args))
+        .send()
+        .await?;
+    let status = response.status().as_u16();
+    let favorites = response.json::<FavoritesResponse>().await?;
+
+    let mut items = if favorites.data.is_empty() {
+        Vec::new()
+    } else {
+        get_item_details(
+            session,
+            favorites
+                .data
+                .iter()
+                .map(|favorite| ItemDetailsQueryItem {
+                    item_type: "Asset".to_string(),
+                    id: favorite.id,
+                })
+                .collect(),
+        )
+        .await?
+    };
+
+    // The search filters by price on its own, favorites have to be filtered here
+    items.retain(|item| {
+        item.price
+            .is_some_and(|price| price <= session.args.max_price)
+    });
+
+    Ok(MarketplaceQueryResponse {
+        next_page_cursor: favorites.next_page_cursor,
+        data: Some(items),
+        errors: None,
+        total_results: None,
+        status,
+        page_size: FAVORITES_PAGE_SIZE,
+    })
+}
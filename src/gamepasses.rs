@@ -0,0 +1,125 @@
+use crate::{
+    get_cookie, sanitize_name, MarketplaceQueryResponse, MarketplaceQueryResponseItem, Session,
+};
+use serde::Deserialize;
+
+const GAMEPASSES_PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Gamepass {
+    id: u64,
+    name: String,
+    product_id: Option<u64>,
+    price: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GamepassesResponse {
+    next_page_cursor: Option<String>,
+    data: Vec<Gamepass>,
+}
+
+#[derive(Deserialize)]
+struct UniverseCreator {
+    id: u64,
+    #[serde(rename = "type")]
+    creator_type: String,
+}
+
+#[derive(Deserialize)]
+struct Universe {
+    creator: UniverseCreator,
+}
+
+#[derive(Deserialize)]
+struct UniversesResponse {
+    data: Vec<Universe>,
+}
+
+/// The seller of a gamepass is the creator of its experience, which the
+/// gamepass list doesn't include
+async fn get_universe_creator(
+    session: &Session,
+    universe_id: u64,
+) -> Result<UniverseCreator, Box<dyn std::error::Error>> {
+    let universes = session
+        .client
+        .get(format!(
+            "{}/v1/games?universeIds={}",
+            session.args.games_base_url, universe_id
+        ))
+        .send()
+        .await?
+        .json::<UniversesResponse>()
+        .await?;
+
+    universes
+        .data
+        .into_iter()
+        .next()
+        .map(|universe| universe.creator)
+        .ok_or_else(|| format!("Universe {} does not exist", universe_id).into())
+}
+
+/// Reads a page of the gamepasses of an experience in the shape of a search
+/// page, so they go through the same product purchase as catalog items
+pub async fn get_gamepasses_page(
+    session: &Session,
+    universe_id: u64,
+    next_page_cursor: &Option<String>,
+) -> Result<MarketplaceQueryResponse, Box<dyn std::error::Error>> {
+    let creator = get_universe_creator(session, universe_id).await?;
+    let response = session
+        .client
+        .get(format!(
+            "{}/v1/games/{}/game-passes?limit={}&sortOrder=Asc&cursor={}",
+            session.args.games_base_url,
+            universe_id,
+            GAMEPASSES_PAGE_SIZE,
+            next_page_cursor.as_deref().unwrap_or_default()
+        ))
+        .header("Cookie", get_cookie(&session.args))
+        .send()
+        .await?;
+    let status = response.status().as_u16();
+    let gamepasses = response.json::<GamepassesResponse>().await?;
+
+    // Gamepasses that aren't for sale have no price or product
+    let items = gamepasses
+        .data
+        .into_iter()
+        .filter(|gamepass| {
+            gamepass
+                .price
+                .is_some_and(|price| price <= session.args.max_price)
+        })
+        .filter_map(|gamepass| {
+            Some(MarketplaceQueryResponseItem {
+                id: gamepass.id,
+                name: sanitize_name(&gamepass.name),
+                product_id: gamepass.product_id?,
+                creator_type: creator.creator_type.clone(),
+                creator_target_id: creator.id,
+                price: gamepass.price,
+                item_type: "GamePass".to_string(),
+                favorite_count: None,
+                collectible_item_id: None,
+                units_available_for_consumption: None,
+                price_status: None,
+                off_sale_deadline: None,
+                details: serde_json::Map::new(),
+            })
+        })
+        .collect();
+
+    Ok(MarketplaceQueryResponse {
+        next_page_cursor: gamepasses.next_page_cursor,
+        data: Some(items),
+        errors: None,
+        total_results: None,
+        status,
+        page_size: GAMEPASSES_PAGE_SIZE,
+    })
+}
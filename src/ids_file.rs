@@ -0,0 +1,98 @@
+use crate::{get_item_details, ItemDetailsQueryItem, MarketplaceQueryResponse, Session};
+use colored::*;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+    sync::atomic::Ordering,
+};
+
+// Ids read from the file for each page, resolved with one details request
+const IDS_PAGE_SIZE: usize = 100;
+
+/// Reads the next page of asset ids from the file, starting at the byte
+/// offset in `next_page_cursor`, and resolves their details. Only a page of
+/// the file is held at once, whatever its size. Blank lines and lines
+/// starting with `#` are skipped, malformed ones are counted as ignored
+pub async fn get_ids_page(
+    session: &Session,
+    path: &Path,
+    next_page_cursor: &Option<String>,
+) -> Result<MarketplaceQueryResponse, Box<dyn std::error::Error>> {
+    let offset = next_page_cursor
+        .as_deref()
+        .map(|cursor| cursor.parse::<u64>())
+        .transpose()
+        .map_err(|_| format!("Invalid position in the ids file {:?}", next_page_cursor))?
+        .unwrap_or(0);
+
+    let mut file = File::open(path)
+        .map_err(|error| format!("Failed to read ids file {}: {}", path.display(), error))?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file);
+
+    let mut ids = Vec::with_capacity(IDS_PAGE_SIZE);
+    let mut position = offset;
+    let mut line = String::new();
+
+    while ids.len() < IDS_PAGE_SIZE {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+
+        if read == 0 {
+            break;
+        }
+
+        position += read as u64;
+        let value = line.trim();
+
+        if value.is_empty() || value.starts_with('#') {
+            continue;
+        }
+
+        match value.parse::<u64>() {
+            Ok(id) => ids.push(id),
+            Err(_) => {
+                session.ignored_ids.fetch_add(1, Ordering::Relaxed);
+
+                if session.args.verbose {
+                    println!(
+                        "{}",
+                        format!("Ignoring malformed id {:?}", value).truecolor(150, 150, 150)
+                    );
+                }
+            }
+        }
+    }
+
+    let at_end = reader.fill_buf()?.is_empty();
+    let mut items = if ids.is_empty() {
+        Vec::new()
+    } else {
+        get_item_details(
+            session,
+            ids.iter()
+                .map(|&id| ItemDetailsQueryItem {
+                    item_type: "Asset".to_string(),
+                    id,
+                })
+                .collect(),
+        )
+        .await?
+    };
+
+    // The search filters by price on its own, listed ids have to be filtered here
+    items.retain(|item| {
+        item.price
+            .is_some_and(|price| price <= session.args.max_price)
+    });
+
+    Ok(MarketplaceQueryResponse {
+        next_page_cursor: (!at_end).then(|| position.to_string()),
+        data: Some(items),
+        errors: None,
+        total_results: None,
+        status: 200,
+        page_size: IDS_PAGE_SIZE,
+    })
+}
@@ -0,0 +1,83 @@
+use std::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::Poll,
+};
+
+/// Runs the futures concurrently, at most `limit` at a time, and returns
+/// their outputs in the order the futures were given
+pub async fn join_bounded<F: Future>(
+    futures: impl IntoIterator<Item = F>,
+    limit: usize,
+) -> Vec<F::Output> {
+    let mut pending = futures.into_iter().enumerate();
+    let mut running: Vec<(usize, Pin<Box<F>>)> = Vec::new();
+    let mut outputs: Vec<Option<F::Output>> = Vec::new();
+
+    poll_fn(|cx| loop {
+        while running.len() < limit.max(1) {
+            let Some((index, future)) = pending.next() else {
+                break;
+            };
+            outputs.push(None);
+            running.push((index, Box::pin(future)));
+        }
+
+        if running.is_empty() {
+            return Poll::Ready(());
+        }
+
+        let before = running.len();
+        running.retain_mut(|(index, future)| match future.as_mut().poll(cx) {
+            Poll::Ready(output) => {
+                outputs[*index] = Some(output);
+                false
+            }
+            Poll::Pending => true,
+        });
+
+        // Nothing finished, every running future will wake the task
+        if running.len() == before {
+            return Poll::Pending;
+        }
+    })
+    .await;
+
+    outputs.into_iter().map(Option::unwrap).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn outputs_keep_their_order_with_at_most_limit_running() {
+        let running = AtomicUsize::new(0);
+        let most_running = AtomicUsize::new(0);
+
+        let outputs = join_bounded(
+            (0..10u64).map(|index| {
+                let (running, most_running) = (&running, &most_running);
+                async move {
+                    let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most_running.fetch_max(now_running, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10 - index)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    index * 2
+                }
+            }),
+            3,
+        )
+        .await;
+
+        assert_eq!(
+            outputs,
+            (0..10).map(|index| index * 2).collect::<Vec<u64>>()
+        );
+        assert_eq!(most_running.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,28 +1,553 @@
 use async_recursion::async_recursion;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use collectibles::{
+    generate_idempotency_key, get_cheapest_resale, get_collectible_product_id,
+    get_collectible_purchase_errors, purchase_collectible, CollectiblePurchaseQuery,
+};
 use colored::*;
-use reqwest::{Body, Client, Error, Response};
+use config::Config;
+use creator_cooldown::CreatorCooldowns;
+use deferred::DeferredRetries;
+use events::{EventKind, EventLog, PurchaseEvent};
+use failures::FailuresFile;
+use network::Unreachable;
+use outcome::{ApiErrorInfo, ItemSummary, PurchaseOutcome};
+use progress::{format_duration, Progress};
+use rate::RateLimiter;
+use receipts::ReceiptsFile;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_LANGUAGE, AUTHORIZATION, COOKIE},
+    Body, Client, Error, RequestBuilder, Response, StatusCode,
+};
+use retry::{get_retry_policy, validate_retry_codes, RetryPolicy};
+use reverse::ReversedPages;
+use sample::Sampler;
+use schedule::Timestamp;
+use seen::{BloomFilter, SeenItems};
 use select::{document::Document, predicate::Name};
 use serde::{Deserialize, Serialize};
-use std::{thread, time::Duration};
+use spend_rate::SpendWindow;
+use state::Checkpoint;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, IsTerminal, Write},
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use table::LiveTable;
 use terminal_hyperlink::Hyperlink;
+use throttle::{escalated_cooldown, warm_up_delay, AdaptiveThrottle};
+use timings::Timings;
+use tokio::{process, sync::mpsc};
+
+// Shadows std's println! everywhere in the crate, so a closed stdout stops
+// the output instead of panicking. Must come before the modules using it
+macro_rules! println {
+    () => {
+        $crate::output::print_line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print_line(format_args!($($arg)*))
+    };
+}
+
+mod account;
+mod audit;
+mod benchmark;
+mod body;
+#[cfg(test)]
+mod cassette;
+mod categories;
+mod collectibles;
+mod config;
+mod creator_cooldown;
+mod creator_store;
+mod deferred;
+mod doctor;
+mod dump;
+mod events;
+mod export;
+mod failures;
+mod favorites;
+mod gamepasses;
+mod ids_file;
+mod join;
+mod manifest;
+#[cfg(test)]
+mod mock_server;
+mod network;
+mod outcome;
+mod output;
+mod ownership;
+mod pause;
+mod progress;
+mod rate;
+mod receipts;
+mod retry;
+mod reverse;
+mod sample;
+mod schedule;
+mod seen;
+mod spend_rate;
+mod state;
+mod table;
+mod throttle;
+mod timings;
+
+const CATALOG_BASE_URL: &str = "https://catalog.roblox.com";
+const USERS_BASE_URL: &str = "https://users.roblox.com";
+const INVENTORY_BASE_URL: &str = "https://inventory.roblox.com";
+const ECONOMY_BASE_URL: &str = "https://economy.roblox.com";
+const WWW_BASE_URL: &str = "https://www.roblox.com";
+const APIS_BASE_URL: &str = "https://apis.roblox.com";
+const PREMIUM_FEATURES_BASE_URL: &str = "https://premiumfeatures.roblox.com";
+const GROUPS_BASE_URL: &str = "https://groups.roblox.com";
+const GAMES_BASE_URL: &str = "https://games.roblox.com";
+const USERMODERATION_BASE_URL: &str = "https://usermoderation.roblox.com";
+
+const CSRF_TOKEN_HEADER: &str = "x-csrf-token";
+// Tries of each way of getting a CSRF token at startup
+const CSRF_FETCH_ATTEMPTS: u32 = 3;
+const CSRF_RETRY_DELAY: Duration = Duration::from_secs(1);
+const CHALLENGE_TYPE_HEADER: &str = "rblx-challenge-type";
+const CHALLENGE_ID_HEADER: &str = "rblx-challenge-id";
+const CHALLENGE_METADATA_HEADER: &str = "rblx-challenge-metadata";
+const TWO_STEP_VERIFICATION_CHALLENGE: &str = "twostepverification";
+const BOUND_AUTH_TOKEN_HEADER: &str = "x-bound-auth-token";
+
+const RATELIMIT_ERROR_CODE: u32 = 27;
+const NOT_FOR_SALE_REASON: &str = "NotForSale";
+const ALREADY_OWNED_REASON: &str = "AlreadyOwned";
+const PRICE_CHANGED_REASON: &str = "PriceChanged";
+const OFF_SALE_PRICE_STATUS: &str = "Off Sale";
+
+const SEARCH_PAGE_SIZE: usize = 120;
+const SEARCH_ATTEMPTS: u32 = 3;
+const SEARCH_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+// Characters of an unreadable response body to show when reporting it
+const BODY_SNIPPET_LENGTH: usize = 200;
+
+// Every matched item was purchased, owned or skipped on purpose
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_ERROR: i32 = 1;
+// The run finished but gave up on some items
+const EXIT_PARTIAL_FAILURE: i32 = 2;
+// The run was stopped intentionally, e.g. by --fail-fast
+const EXIT_ABORTED: i32 = 3;
+// Roblox couldn't be reached, the network is down or blocks it
+const EXIT_UNREACHABLE: i32 = 4;
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+/// An intentional stop of the run, as opposed to an unexpected error
+#[derive(Debug)]
+struct Aborted(String);
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+#[derive(PartialEq, Eq)]
+enum Availability {
+    Available,
+    Owned,
+    CreatedByRoblox,
+    // Uploaded by the account itself, which can't purchase it
+    OwnCreation,
+    // The ownership check failed and --on-owned-check-error is skip
+    Unknown,
+}
+
+enum PurchaseStatus {
+    Purchased,
+    Skipped,
+    SaleEnded,
+    AlreadyOwned,
+    NotFound,
+    Moderated,
+    // Ratelimited with --defer-ratelimited, to be tried again after the cooldown
+    Ratelimited,
+    Failed,
+}
+
+impl PurchaseStatus {
+    fn event_kind(&self) -> EventKind {
+        match self {
+            PurchaseStatus::Purchased => EventKind::Purchased,
+            PurchaseStatus::Skipped => EventKind::Skipped,
+            PurchaseStatus::SaleEnded => EventKind::SaleEnded,
+            PurchaseStatus::AlreadyOwned => EventKind::AlreadyOwned,
+            PurchaseStatus::NotFound => EventKind::NotFound,
+            PurchaseStatus::Moderated => EventKind::Moderated,
+            PurchaseStatus::Ratelimited => EventKind::Ratelimited,
+            PurchaseStatus::Failed => EventKind::Failed,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum PurchaseRoute {
+    Economy,
+    Collectible { collectible_product_id: String },
+}
+
+/// State shared by every request made on behalf of the authenticated user
+struct Session {
+    client: Client,
+    args: Args,
+    timings: Arc<Timings>,
+    user_id: u64,
+    csrf_token: Mutex<String>,
+    // By item type and id, assets and bundles are numbered apart
+    purchase_routes: Mutex<HashMap<(String, u64), PurchaseRoute>>,
+    throttle: Option<AdaptiveThrottle>,
+    retries: AtomicU32,
+    spent: AtomicU64,
+    ratelimits: AtomicU32,
+    ratelimit_wait: Mutex<Duration>,
+    event_log: Option<EventLog>,
+    live_table: Option<Mutex<LiveTable>>,
+    // Purchased in an earlier run according to the event log, with --skip-reported
+    reported_ids: HashSet<u64>,
+    // Malformed lines of --ids-file
+    ignored_ids: AtomicU32,
+    // Purchase intervals handed out so far, for --warm-up-steps
+    warm_up_step: AtomicU32,
+    // Ownership of the current page from the batch check, by item type and id
+    page_ownership: Mutex<HashMap<(String, u64), bool>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    // Ratelimits since the last successful purchase
+    ratelimit_streak: AtomicU32,
+    receipts: Option<ReceiptsFile>,
+    // Recent spends for --max-spend-rate
+    spend_window: Option<SpendWindow>,
+    // Last error of the purchase being attempted, for its outcome
+    purchase_error: Mutex<Option<ApiErrorInfo>>,
+    // Purchases --confirm-purchases failed to check the ownership of
+    unconfirmed: AtomicU32,
+    // Whether the purchase being attempted was already checked for moderation
+    moderation_checked: AtomicBool,
+}
+
+impl Session {
+    fn new(
+        client: Client,
+        args: Args,
+        timings: Arc<Timings>,
+        user_id: u64,
+        csrf_token: String,
+    ) -> Self {
+        let throttle = args.throttle_on_403.then(|| {
+            AdaptiveThrottle::new(
+                Duration::from_millis(args.throttle_min_ms),
+                Duration::from_millis(args.throttle_max_ms),
+            )
+        });
+        let spend_window = args.max_spend_rate.map(SpendWindow::new);
+
+        Session {
+            client,
+            args,
+            timings,
+            user_id,
+            csrf_token: Mutex::new(csrf_token),
+            purchase_routes: Mutex::new(HashMap::new()),
+            throttle,
+            retries: AtomicU32::new(0),
+            spent: AtomicU64::new(0),
+            ratelimits: AtomicU32::new(0),
+            ratelimit_wait: Mutex::new(Duration::ZERO),
+            event_log: None,
+            live_table: None,
+            reported_ids: HashSet::new(),
+            ignored_ids: AtomicU32::new(0),
+            unconfirmed: AtomicU32::new(0),
+            moderation_checked: AtomicBool::new(false),
+            warm_up_step: AtomicU32::new(0),
+            page_ownership: Mutex::new(HashMap::new()),
+            spend_window,
+            purchase_error: Mutex::new(None),
+            rate_limiter: None,
+            ratelimit_streak: AtomicU32::new(0),
+            receipts: None,
+        }
+    }
+
+    fn get_csrf_token(&self) -> String {
+        self.csrf_token.lock().unwrap().clone()
+    }
+
+    fn set_csrf_token(&self, csrf_token: String) {
+        *self.csrf_token.lock().unwrap() = csrf_token;
+    }
+
+    /// Counts a purchase retry against the run-wide budget, aborting the run
+    /// once it's spent so a degraded API doesn't turn into a retry storm
+    fn record_retry(&self) -> Result<(), Aborted> {
+        let retries = self.retries.fetch_add(1, Ordering::Relaxed) + 1;
+
+        match self.args.total_retries {
+            Some(total_retries) if retries > total_retries => Err(Aborted(format!(
+                "Used up the retry budget of {} retries",
+                total_retries
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Waits out a ratelimit, longer and longer once they keep coming in a
+    /// row despite the cooldown
+    async fn wait_for_ratelimit(&self, ratelimit_interval: Duration) {
+        let streak = self.ratelimit_streak.fetch_add(1, Ordering::Relaxed) + 1;
+        let cooldown = escalated_cooldown(
+            ratelimit_interval,
+            streak,
+            self.args.escalate_cooldown_after,
+            Duration::from_secs(self.args.max_cooldown),
+        );
+
+        if cooldown > ratelimit_interval {
+            println!(
+                "{} Ratelimited {} times in a row, cooling down for {}",
+                "Warning".bold().yellow(),
+                streak,
+                format_duration(cooldown)
+            );
+        }
+
+        if self.shows(EventKind::Ratelimited) && !self.args.quiet {
+            wait_for_ratelimit(cooldown).await;
+        } else {
+            tokio::time::sleep(cooldown).await;
+        }
+
+        self.ratelimits.fetch_add(1, Ordering::Relaxed);
+        *self.ratelimit_wait.lock().unwrap() += cooldown;
+        self.log_event(&PurchaseEvent::Ratelimited {
+            waited_ms: cooldown.as_millis() as u64,
+        });
+    }
+
+    fn record_receipt(
+        &self,
+        asset: &MarketplaceQueryResponseItem,
+        price: u32,
+        transaction_id: &str,
+    ) {
+        if self.args.verbose {
+            println!(
+                "{}",
+                format!("Transaction {} for {}", transaction_id, asset.name)
+                    .truecolor(150, 150, 150)
+            );
+        }
+
+        if let Some(receipts) = &self.receipts {
+            if let Err(error) = receipts.record(asset.id, price, transaction_id) {
+                println!(
+                    "{} Failed to write to the receipts file: {}",
+                    "Warning".bold().yellow(),
+                    error
+                );
+            }
+        }
+    }
+
+    /// Whether --show lets events of this kind through, all of them pass
+    /// when it isn't set
+    fn shows(&self, kind: EventKind) -> bool {
+        self.args.show.is_empty() || self.args.show.contains(&kind)
+    }
+
+    /// Prints a line about an item unless --show hides events of its kind,
+    /// or --quiet does since it isn't a failure
+    fn report(&self, kind: EventKind, line: fmt::Arguments) {
+        if self.shows(kind) && (!self.args.quiet || kind == EventKind::Failed) {
+            println!("{}", line);
+        }
+    }
+
+    fn log_event(&self, event: &PurchaseEvent) {
+        if event.kind().is_some_and(|kind| !self.shows(kind)) {
+            return;
+        }
+
+        if let Some(event_log) = &self.event_log {
+            if let Err(error) = event_log.write(event) {
+                println!(
+                    "{} Failed to write to the event log: {}",
+                    "Warning".bold().yellow(),
+                    error
+                );
+            }
+        }
+    }
+
+    /// Purchases are paid by the group from --use-group-funds if set
+    fn get_purchaser_id(&self) -> u64 {
+        self.args.use_group_funds.unwrap_or(self.user_id)
+    }
+
+    fn get_purchaser_type(&self) -> &'static str {
+        match self.args.use_group_funds {
+            Some(_) => "Group",
+            None => "User",
+        }
+    }
+
+    fn can_afford(&self, price: u32) -> bool {
+        self.args
+            .max_spend
+            .is_none_or(|max_spend| self.spent.load(Ordering::Relaxed) + price as u64 <= max_spend)
+    }
+
+    fn set_purchase_error(&self, code: Option<u32>, message: impl Into<String>) {
+        *self.purchase_error.lock().unwrap() = Some(ApiErrorInfo {
+            code,
+            message: message.into(),
+        });
+    }
+
+    fn record_spend(&self, price: u32) -> Result<(), Aborted> {
+        self.spent.fetch_add(price as u64, Ordering::Relaxed);
+
+        let Some(spend_window) = &self.spend_window else {
+            return Ok(());
+        };
+
+        match spend_window.record(price as u64, Instant::now()) {
+            Some(spent) => Err(Aborted(format!(
+                "Spent {} Robux in the last minute, more than the --max-spend-rate of {} Robux per minute",
+                spent,
+                self.args.max_spend_rate.unwrap_or_default()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn get_purchase_interval(&self, interval: Duration) -> Duration {
+        // The throttle only ever slows the run down, and recovers towards the
+        // configured interval rather than below it
+        let interval = match &self.throttle {
+            Some(throttle) => throttle.delay().max(interval),
+            None => interval,
+        };
+
+        if self.args.warm_up_steps == 0 {
+            return interval;
+        }
+
+        warm_up_delay(
+            interval,
+            Duration::from_millis(self.args.warm_up_ms),
+            self.warm_up_step.fetch_add(1, Ordering::Relaxed),
+            self.args.warm_up_steps,
+        )
+    }
+}
+
+#[derive(Default, Serialize)]
+struct RunSummary {
+    purchased: u32,
+    skipped: u32,
+    sale_ended: u32,
+    already_owned: u32,
+    failed: u32,
+    failed_pages: u32,
+    not_found: u32,
+    not_found_streaks: u32,
+    creator_reorders: u32,
+    moderated: u32,
+    deferred: u32,
+    ignored_ids: u32,
+    unconfirmed: u32,
+    retries: u32,
+    ratelimits: u32,
+    #[serde(rename = "ratelimit_wait_ms", serialize_with = "serialize_millis")]
+    ratelimit_wait: Duration,
+    spent: u64,
+    purchases_per_creator: HashMap<u64, u32>,
+    user_ids: Vec<u64>,
+    // Only kept for --reconcile-ownership
+    #[serde(skip)]
+    purchased_items: Vec<MarketplaceQueryResponseItem>,
+}
+
+impl RunSummary {
+    /// Adds the counts of another run, e.g. of another account
+    fn add(&mut self, other: &RunSummary) {
+        self.purchased += other.purchased;
+        self.skipped += other.skipped;
+        self.sale_ended += other.sale_ended;
+        self.already_owned += other.already_owned;
+        self.failed += other.failed;
+        self.failed_pages += other.failed_pages;
+        self.not_found += other.not_found;
+        self.not_found_streaks += other.not_found_streaks;
+        self.creator_reorders += other.creator_reorders;
+        self.moderated += other.moderated;
+        self.deferred += other.deferred;
+        self.ignored_ids += other.ignored_ids;
+        self.unconfirmed += other.unconfirmed;
+        self.retries += other.retries;
+        self.ratelimits += other.ratelimits;
+        self.ratelimit_wait += other.ratelimit_wait;
+        self.spent += other.spent;
+        self.user_ids.extend(&other.user_ids);
+    }
+}
+
+fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ApiError {
     code: u32,
+    message: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct MarketplaceQueryResponseItem {
     id: u64,
+    #[serde(deserialize_with = "deserialize_item_name")]
     name: String,
     product_id: u64,
     creator_type: String,
     creator_target_id: u64,
     price: Option<u32>,
     item_type: String,
+    favorite_count: Option<u64>,
+    collectible_item_id: Option<String>,
+    units_available_for_consumption: Option<u64>,
+    price_status: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_off_sale_deadline",
+        skip_serializing_if = "Option::is_none"
+    )]
+    off_sale_deadline: Option<Timestamp>,
+    /// Remaining details returned by the search, kept for dumps
+    #[serde(flatten)]
+    details: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize)]
@@ -30,6 +555,15 @@ struct MarketplaceQueryResponseItem {
 struct MarketplaceQueryResponse {
     next_page_cursor: Option<String>,
     data: Option<Vec<MarketplaceQueryResponseItem>>,
+    errors: Option<Vec<ApiError>>,
+    // Only some search responses include this, most are purely cursor-based
+    total_results: Option<u64>,
+    #[serde(skip)]
+    status: u16,
+    // Items asked for, which the search lowers when large pages come back
+    // malformed
+    #[serde(skip)]
+    page_size: usize,
 }
 
 #[derive(Serialize)]
@@ -38,18 +572,26 @@ struct AssetPurchaseQuery {
     expected_currency: u8,
     expected_price: u32,
     expected_seller_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_purchaser_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_purchaser_type: Option<String>,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct AssetPurchaseResponse {
     errors: Option<Vec<ApiError>>,
+    reason: Option<String>,
+    // Missing from some responses, and a number in others
+    #[serde(default, deserialize_with = "deserialize_transaction_id")]
+    transaction_id: Option<String>,
 }
 
-impl Into<Body> for AssetPurchaseQuery {
-    fn into(self) -> Body {
+impl From<AssetPurchaseQuery> for Body {
+    fn from(query: AssetPurchaseQuery) -> Body {
         let json_string =
-            serde_json::to_string(&self).expect("Failed to serialize AssetPurchaseQuery");
+            serde_json::to_string(&query).expect("Failed to serialize AssetPurchaseQuery");
         Body::from(json_string)
     }
 }
@@ -58,251 +600,5308 @@ impl Into<Body> for AssetPurchaseQuery {
 #[serde(rename_all = "camelCase")]
 struct AuthenticatedUserResponse {
     id: u64,
+    name: String,
+    display_name: String,
 }
 
-#[derive(Parser)]
-#[command(author, version, about)]
-struct Args {
-    /// Category of assets
-    #[arg(short, long)]
-    category: Option<String>,
+/// Where the items to purchase come from
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum Source {
+    /// Avatar items from the catalog search, or your favorites with --favorites
+    Catalog,
+    /// Gamepasses of the experience given with --universe-id
+    Gamepasses,
+}
 
-    /// Subcategory of assets
-    #[arg(short, long)]
-    subcategory: Option<String>,
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum PurchaseOrder {
+    /// Lowest priced items first, to buy the most items within --max-spend
+    Cheapest,
+    /// Most recently uploaded items first
+    Newest,
+    /// Most favorited items first
+    Favorites,
+    /// Items whose sale closes soonest first
+    Expiring,
+    /// The order returned by the search
+    AsListed,
+}
 
-    /// .ROBLOSECURITY cookie to purchase assets
-    #[arg(short, long)]
-    auth: String,
+/// How the outcome of each purchase is shown
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Format {
+    /// A line for each item as it's handled
+    Lines,
+    /// A table of the latest purchases with running totals, redrawn in place
+    Table,
 }
 
-fn get_search_url(args: &Args, next_page_cursor: &Option<String>) -> String {
-    let category = args.category.clone();
-    let subcategory = args.subcategory.clone();
-    format!(
-        "https://catalog.roblox.com/v2/search/items/details?category={}&subcategory={}&maxPrice=0&limit=120&cursor={}",
-        category.unwrap_or("".to_string()), subcategory.unwrap_or("".to_string()), next_page_cursor.clone().unwrap_or("".to_string())
-    )
+/// Kind of account an item was published by
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum CreatorType {
+    User,
+    Group,
 }
 
-async fn get_authenticated_user(
-    client: &Client,
-    auth: &String,
-) -> Result<AuthenticatedUserResponse, Error> {
-    client
-        .get("https://users.roblox.com/v1/users/authenticated")
-        .header("Cookie", format!(".ROBLOSECURITY={}", auth))
-        .send()
-        .await?
-        .json::<AuthenticatedUserResponse>()
-        .await
+impl CreatorType {
+    /// The name the catalog uses for it, e.g. in `creatorType`
+    fn get_api_name(&self) -> &'static str {
+        match self {
+            CreatorType::User => "User",
+            CreatorType::Group => "Group",
+        }
+    }
 }
 
-async fn authenticated_user_owns_bundle(
-    client: &Client,
-    auth: &String,
-    item: &MarketplaceQueryResponseItem,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let authenticated_user_id = get_authenticated_user(client, auth).await?.id;
-    let user_owns_bundle = client
-        .get(format!(
-            "https://inventory.roblox.com/v1/users/{}/items/{}/{}/is-owned",
-            authenticated_user_id, item.item_type, item.id
-        ))
-        .header("Cookie", format!(".ROBLOSECURITY={}", auth))
-        .send()
-        .await?
-        .json::<serde_json::Value>()
-        .await?;
+/// Which marketplace API collectibles are bought through
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum PurchaseApi {
+    /// marketplace-sales v1, buys collectibles and their resales
+    Legacy,
+    /// The newer marketplace-sales v2 flow, collectibles and resales only.
+    /// Experimental
+    V2,
+}
 
-    Ok(user_owns_bundle.as_bool().unwrap())
+/// What to do with an item when checking its ownership fails
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum OwnedCheckErrorAction {
+    /// Skip the item with a warning
+    Skip,
+    /// Try to purchase the item anyway
+    Attempt,
+    /// Stop the run
+    Abort,
 }
 
-async fn get_csrf_token(
-    client: &Client,
-    auth: &String,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let body = client
-        .get("https://www.roblox.com/home")
-        .header("Cookie", format!(".ROBLOSECURITY={}", auth))
-        .send()
-        .await?
-        .text()
-        .await?;
+/// A slice of the search pages, written as `<index>/<count>` with a 1-based index
+#[derive(Clone, Copy)]
+struct Shard {
+    index: u32,
+    count: u32,
+}
 
-    let document = Document::from_read(body.as_bytes()).unwrap();
+impl Shard {
+    fn includes_page(&self, page_index: u32) -> bool {
+        page_index % self.count == self.index - 1
+    }
+}
 
-    for node in document.find(Name("meta")) {
-        if let Some(attr) = node.attr("name") {
-            if attr == "csrf-token" {
-                if let Some(csrf_value) = node.attr("data-token") {
-                    return Ok(csrf_value.to_string());
-                }
-            }
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (index, count) = value
+            .split_once('/')
+            .ok_or("expected a shard in the form <index>/<count>")?;
+        let index = index
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| "shard index must be a number")?;
+        let count = count
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| "shard count must be a number")?;
+
+        if count == 0 || index == 0 || index > count {
+            return Err(format!("shard index must be between 1 and {}", count));
         }
-    }
 
-    Ok(String::new())
+        Ok(Shard { index, count })
+    }
 }
 
-async fn is_asset_available(
-    client: &Client,
-    auth: &String,
-    asset: &MarketplaceQueryResponseItem,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    if authenticated_user_owns_bundle(client, auth, asset).await? {
-        return Ok(false);
+impl Serialize for Shard {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{}/{}", self.index, self.count))
     }
+}
 
-    if asset.creator_type == "User" && asset.creator_target_id == 1 {
-        return Ok(false);
+impl<'de> Deserialize<'de> for Shard {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
     }
+}
 
-    Ok(true)
+/// An extra request header, written as `Name: Value`
+#[derive(Clone)]
+struct CustomHeader {
+    name: HeaderName,
+    value: HeaderValue,
 }
 
-async fn purchase_asset(
-    client: &Client,
-    asset: &MarketplaceQueryResponseItem,
-    auth: &String,
-    csrf_token: &String,
-) -> Result<Response, Error> {
-    client
-        .post(format!(
-            "https://economy.roblox.com/v1/purchases/products/{}",
-            asset.product_id
-        ))
-        .body(AssetPurchaseQuery {
-            expected_currency: 1,
-            expected_price: 0,
-            expected_seller_id: asset.creator_target_id,
-        })
-        .header("Content-Type", "application/json; charset=utf-8")
-        .header("Cookie", format!(".ROBLOSECURITY={}", auth))
-        .header("X-CSRF-TOKEN", csrf_token)
-        .send()
-        .await
+impl FromStr for CustomHeader {
+    type Err = String;
+
+    fn from_str(header: &str) -> Result<Self, Self::Err> {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or("expected a header in the form Name: Value")?;
+        let name = HeaderName::from_str(name.trim())
+            .map_err(|_| format!("invalid header name {:?}", name.trim()))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|_| format!("invalid value for header {}", name))?;
+
+        Ok(CustomHeader { name, value })
+    }
 }
 
-#[async_recursion(?Send)]
-async fn attempt_purchase(
-    client: &Client,
-    asset: &MarketplaceQueryResponseItem,
-    args: &Args,
-    csrf_token: &String,
-    interval: Duration,
-    ratelimit_interval: Duration,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let asset_link = asset
-        .name
-        .hyperlink(format!("https://www.roblox.com/bundles/{}", asset.id));
+/// Headers that carry credentials are written without their value
+impl Serialize for CustomHeader {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = if [COOKIE, AUTHORIZATION].contains(&self.name)
+            || self.name == CSRF_TOKEN_HEADER
+            || self.name == BOUND_AUTH_TOKEN_HEADER
+        {
+            "[redacted]"
+        } else {
+            self.value.to_str().unwrap_or_default()
+        };
 
-    if asset.price.is_none() {
-        println!("{} has no price", asset_link.truecolor(150, 150, 150));
-        return Ok(());
+        serializer.collect_str(&format_args!("{}: {}", self.name, value))
     }
+}
 
-    if let Ok(purchase_response) = purchase_asset(client, asset, &args.auth, csrf_token).await {
-        let purchase_body = purchase_response.json::<AssetPurchaseResponse>().await?;
+impl<'de> Deserialize<'de> for CustomHeader {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
 
-        if purchase_body.errors.is_some() {
-            for error in purchase_body.errors.unwrap().iter() {
-                if error.code == 27 {
-                    println!("{}", "Ratelimit reached. Waiting 65 seconds..".red());
-                    thread::sleep(ratelimit_interval);
-                } else {
-                    println!("{} {}", "Failed to purchase".bold().red(), asset_link);
-                }
-            }
+#[derive(Subcommand, Clone)]
+enum Command {
+    /// Crawl the catalog without checking ownership or purchasing
+    Search {
+        /// Write every item found to this file as a JSON array
+        #[arg(long)]
+        dump: PathBuf,
+    },
+    /// Check which items of the search are owned without purchasing
+    Audit {
+        /// Write the ids of unowned items to this file, one per line
+        #[arg(long)]
+        unowned: Option<PathBuf>,
+    },
+    /// Write the id and name of every UGC item the account owns to a file,
+    /// one per line, without purchasing
+    ExportOwned {
+        /// File to write the owned items to
+        path: PathBuf,
+    },
+    /// Try the purchases of a --failures file again, leaving out items that
+    /// failed for good. What fails again goes to a fresh failures file
+    Retry {
+        /// Failures file of an earlier run
+        failures: PathBuf,
+    },
+    /// List or clear the checkpoints saved by --resume
+    State {
+        #[command(subcommand)]
+        action: StateAction,
+    },
+    /// Print the authenticated account with its Robux balance and premium status
+    Whoami,
+    /// Check the cookie, CSRF token, search and ownership check without
+    /// purchasing anything
+    Doctor,
+}
 
-            attempt_purchase(
-                client,
-                asset,
-                args,
-                csrf_token,
-                interval,
-                ratelimit_interval,
-            )
-            .await?;
+#[derive(Subcommand, Clone)]
+enum StateAction {
+    /// Print each checkpoint with its filters and age
+    List,
+    /// Remove checkpoints
+    Clear {
+        /// Only remove checkpoints that weren't updated for this many hours
+        #[arg(long)]
+        older_than_hours: Option<u64>,
+    },
+}
 
-            return Ok(());
-        }
+#[derive(Parser, Serialize, Clone)]
+#[command(author, version, about)]
+#[serde(rename_all = "kebab-case")]
+struct Args {
+    /// Category of assets, either its name or its numeric id
+    #[arg(short, long)]
+    category: Option<String>,
 
-        println!("{} {}", "Purchased".bold().green(), asset_link);
-        thread::sleep(interval);
-    } else {
-        println!("{} {}", "Failed to purchase".bold().red(), asset_link);
-        attempt_purchase(
-            client,
-            asset,
-            args,
-            csrf_token,
-            interval,
-            ratelimit_interval,
-        )
-        .await?;
-        return Ok(());
-    }
+    /// Subcategory of assets, either its name or its numeric id
+    #[arg(short, long)]
+    subcategory: Option<String>,
 
-    Ok(())
-}
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-    let client = Client::new();
+    /// .ROBLOSECURITY cookie to purchase assets
+    #[arg(short, long)]
+    #[serde(serialize_with = "manifest::serialize_redacted")]
+    auth: Option<String>,
 
-    let csrf_token = get_csrf_token(&client, &args.auth).await?;
+    /// File with one .ROBLOSECURITY cookie per line, to purchase with every
+    /// account in turn
+    #[arg(long, conflicts_with = "auth")]
+    auth_file: Option<PathBuf>,
 
-    let interval = Duration::from_secs(1);
-    let ratelimit_interval = Duration::from_secs(65);
+    /// Go through the accounts of --auth-file in a random order
+    #[arg(long, requires = "auth_file")]
+    randomize_accounts: bool,
 
-    let mut next_page_cursor: Option<String> = None;
-    let mut purchased_items: u32 = 0;
+    /// CSRF token to start with instead of scraping one from the website.
+    /// It's replaced automatically once it expires
+    #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    #[serde(serialize_with = "manifest::serialize_redacted")]
+    csrf_token: Option<String>,
 
-    loop {
-        let response = client
-            .get(get_search_url(&args, &next_page_cursor))
-            .send()
-            .await?
-            .json::<MarketplaceQueryResponse>()
-            .await?;
-
-        if response.data.is_none() {
-            println!(
-                "{} Bought {} items",
-                "Done".bold().green(),
-                purchased_items.to_string().bold().blue()
-            );
-            break;
-        }
+    /// x-bound-auth-token header captured from the browser, forwarded with
+    /// each purchase once Roblox requires it
+    #[arg(long)]
+    #[serde(serialize_with = "manifest::serialize_redacted")]
+    bound_auth_token: Option<String>,
 
-        for asset in response.data.unwrap().iter() {
-            if is_asset_available(&client, &args.auth, asset).await? {
-                attempt_purchase(
-                    &client,
-                    asset,
-                    &args,
-                    &csrf_token,
-                    interval,
-                    ratelimit_interval,
-                )
-                .await?;
-                purchased_items += 1;
-            }
-        }
+    /// JSON file with defaults for any of these options
+    #[arg(long)]
+    config: Option<PathBuf>,
 
-        if response.next_page_cursor.is_none() {
-            println!(
-                "{} Bought {} items",
-                "Done".bold().green(),
-                purchased_items.to_string().bold().blue()
-            );
-            break;
-        }
+    /// Number of items requested per search page
+    #[arg(long, default_value_t = SEARCH_PAGE_SIZE, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=SEARCH_PAGE_SIZE as u64))]
+    max_page_size: usize,
 
-        next_page_cursor = response.next_page_cursor;
-    }
+    /// Smallest page size to fall back to when large pages come back malformed
+    #[arg(long, default_value_t = 30, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=SEARCH_PAGE_SIZE as u64))]
+    min_page_size: usize,
 
-    Ok(())
+    /// Number of search pages to fetch in the background while purchasing
+    #[arg(long, default_value_t = 0)]
+    prefetch_pages: usize,
+
+    /// Maximum number of search pages held in memory at once, including the
+    /// page being purchased. Caps --prefetch-pages on large crawls
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u64).range(1..))]
+    max_concurrent_pages: u64,
+
+    /// Purchase the pages from the last to the first. Every page is read
+    /// before purchasing starts, so the end of the catalog comes first
+    #[arg(long, conflicts_with = "resume")]
+    reverse: bool,
+
+    /// Pages --reverse holds in memory at once. Only the cursors of earlier
+    /// pages are kept past this, and those pages are read again once reached
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u64).range(1..))]
+    max_buffered_pages: u64,
+
+    /// Where to find the items to purchase
+    #[arg(long, value_enum, default_value_t = Source::Catalog)]
+    source: Source,
+
+    /// Experience whose gamepasses are purchased with --source gamepasses
+    #[arg(long, value_name = "UNIVERSE_ID")]
+    universe_id: Option<u64>,
+
+    /// Purchase from your favorited items of this asset type (e.g. 8 for
+    /// hats) instead of searching the catalog
+    #[arg(long, value_name = "ASSET_TYPE_ID")]
+    favorites: Option<u32>,
+
+    /// Purchase the assets whose ids are listed in this file, one per line,
+    /// instead of searching the catalog
+    #[arg(long, value_name = "PATH", conflicts_with = "favorites")]
+    ids_file: Option<PathBuf>,
+
+    /// Purchase the items on the store of this user or group, whatever
+    /// their category, instead of searching the catalog
+    #[arg(long, value_name = "CREATOR_ID", conflicts_with_all = ["favorites", "ids_file"])]
+    creator_store: Option<u64>,
+
+    /// Whether the id of --creator-store is a user or a group
+    #[arg(long, value_enum, default_value_t = CreatorType::User)]
+    creator_store_type: CreatorType,
+
+    /// Highest price in Robux of the items to search for and purchase
+    #[arg(long, default_value_t = 0)]
+    max_price: u32,
+
+    /// Also search items that are not for sale, which are left out by the
+    /// search otherwise. They can't be purchased, but show up in audits
+    #[arg(long)]
+    include_not_for_sale: bool,
+
+    /// Stop buying paid items once this many Robux were spent in total
+    #[arg(long)]
+    max_spend: Option<u64>,
+
+    /// Stop the run if more than this many Robux are spent within any
+    /// minute, a safety net against a runaway paid run
+    #[arg(long, value_name = "ROBUX_PER_MINUTE")]
+    max_spend_rate: Option<u64>,
+
+    /// Read the price of paid items again right before buying them and skip
+    /// those that went up by more than this many Robux since the search
+    #[arg(long)]
+    price_tolerance: Option<u32>,
+
+    /// Pay for purchases with the funds of this group. The account needs
+    /// permission to spend them
+    #[arg(long, value_name = "GROUP_ID")]
+    use_group_funds: Option<u64>,
+
+    /// Maximum number of items to purchase from a single creator
+    #[arg(long)]
+    max_per_creator: Option<u32>,
+
+    /// Minimum delay between purchases of items from the same creator. Items
+    /// of other creators are purchased in the meantime
+    #[arg(long, value_name = "MILLISECONDS")]
+    creator_cooldown_ms: Option<u64>,
+
+    /// Order in which the items of each page are purchased
+    #[arg(long, value_enum, default_value = "as-listed", alias = "prioritize")]
+    purchase_order: PurchaseOrder,
+
+    /// Skip items with fewer favorites than this
+    #[arg(long)]
+    min_favorites: Option<u64>,
+
+    /// Skip items with more favorites than this
+    #[arg(long)]
+    max_favorites: Option<u64>,
+
+    /// Only buy items published by these kinds of creators, e.g.
+    /// `--creator-types group` to skip items of users
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [CreatorType::User, CreatorType::Group]
+    )]
+    creator_types: Vec<CreatorType>,
+
+    // Set on a copy of the arguments to search one creator type at a time
+    // once the full search stops early
+    #[arg(skip)]
+    #[serde(skip)]
+    search_slice: Option<CreatorType>,
+
+    /// Only consider this fraction of the items, picked at random before
+    /// their ownership is checked, e.g. `0.1` for about one in ten
+    #[arg(long, value_parser = parse_probability)]
+    sample_rate: Option<f64>,
+
+    /// Seed of --sample-rate, to sample the same items again
+    #[arg(long, requires = "sample_rate")]
+    seed: Option<u64>,
+
+    /// Keep searching for new items after reaching the end of the catalog
+    #[arg(long)]
+    watch: bool,
+
+    /// Wait until this RFC 3339 timestamp before purchasing, e.g.
+    /// `2024-05-01T18:00:00Z`. Combine with --watch to keep buying after a drop
+    #[arg(long)]
+    start_at: Option<Timestamp>,
+
+    /// Remember the items seen in watch mode with a bloom filter of fixed
+    /// size instead of an exact set. Keeps memory bounded on runs lasting
+    /// days, at the cost of rarely skipping a new item
+    #[arg(long)]
+    seen_bloom: bool,
+
+    /// Number of items the --seen-bloom filter is sized for
+    #[arg(long, default_value_t = 1_000_000)]
+    seen_bloom_capacity: u64,
+
+    /// Share of new items --seen-bloom may wrongly skip once full
+    #[arg(long, default_value_t = 0.001, value_parser = parse_probability)]
+    seen_bloom_fp_rate: f64,
+
+    /// Seconds to wait between searches in watch mode
+    #[arg(long, default_value_t = 60)]
+    poll_interval: u64,
+
+    /// Only process every n-th search page, e.g. `2/4` for the second of four
+    /// instances. Cursors are opaque, so every instance still walks all pages
+    /// and sharding is best-effort by page index
+    #[arg(long)]
+    shard: Option<Shard>,
+
+    /// Buy the cheapest resale of sold out collectibles costing up to this
+    /// many Robux
+    #[arg(long)]
+    allow_resale_up_to: Option<u32>,
+
+    /// Seconds to wait for a single purchase request before retrying it
+    #[arg(long, default_value_t = 30)]
+    purchase_timeout_secs: u64,
+
+    /// Abort the run once this many purchase retries were made in total
+    #[arg(long)]
+    total_retries: Option<u32>,
+
+    /// Set ratelimited purchases aside and go on with other items, trying
+    /// them again once the ratelimit cooldown passed
+    #[arg(long)]
+    defer_ratelimited: bool,
+
+    /// Move on to the next page after this many items in a row no longer
+    /// exist, e.g. when the search lists deleted items
+    #[arg(long, value_name = "ITEMS", value_parser = clap::value_parser!(u32).range(1..))]
+    max_404_streak: Option<u32>,
+
+    /// Abort the run on the first purchase failure other than a ratelimit
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Purchase error codes to retry, overriding the built-in handling,
+    /// e.g. `--retry-codes 27,0`
+    #[arg(long, value_name = "CODES", value_delimiter = ',')]
+    retry_codes: Vec<u32>,
+
+    /// Purchase error codes to give up on the item for
+    #[arg(long, value_name = "CODES", value_delimiter = ',')]
+    skip_codes: Vec<u32>,
+
+    /// Purchase error codes to stop the run for
+    #[arg(long, value_name = "CODES", value_delimiter = ',')]
+    abort_codes: Vec<u32>,
+
+    /// Keep going when purchases fail with an error instead of stopping the
+    /// run, moving on to the next page if every purchase on a page failed
+    #[arg(long, conflicts_with = "fail_fast")]
+    skip_failed_pages: bool,
+
+    /// Append the ids of items that failed to purchase to this file, one per
+    /// line followed by a tab and the reason
+    #[arg(long, value_name = "PATH")]
+    failures: Option<PathBuf>,
+
+    /// Append the transaction id of each purchase to this file, one per line
+    /// after the item id and the price paid, separated by tabs
+    #[arg(long, value_name = "PATH")]
+    receipts: Option<PathBuf>,
+
+    /// Stop searching once a full page of items is already owned
+    #[arg(long)]
+    skip_completed_subcategories: bool,
+
+    /// Check that each purchased item is actually owned afterwards
+    #[arg(long)]
+    confirm_purchases: bool,
+
+    /// Attempt every item without checking whether it's already owned first.
+    /// Owned items are refused by the purchase endpoint instead, which saves a
+    /// request per item on fresh accounts
+    #[arg(long)]
+    no_owned_check: bool,
+
+    /// What to do with an item whose ownership check failed
+    #[arg(long, value_enum, default_value = "skip")]
+    on_owned_check_error: OwnedCheckErrorAction,
+
+    /// Check the ownership of every purchased item once the run ends to
+    /// correct the purchase count
+    #[arg(long, requires = "no_owned_check")]
+    reconcile_ownership: bool,
+
+    /// Slow down purchases when requests are refused with 403 or 429 and
+    /// speed back up after a streak of successes
+    #[arg(long)]
+    throttle_on_403: bool,
+
+    /// Shortest delay between purchases when throttling, in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    throttle_min_ms: u64,
+
+    /// Longest delay between purchases when throttling, in milliseconds
+    #[arg(long, default_value_t = 30000)]
+    throttle_max_ms: u64,
+
+    /// Purchases over which the delay ramps down from --warm-up-ms to the
+    /// usual interval at the start of a run, 0 to start at full speed
+    #[arg(long, default_value_t = 0)]
+    warm_up_steps: u32,
+
+    /// Delay after the first purchase of a warm-up, in milliseconds
+    #[arg(long, default_value_t = 8000)]
+    warm_up_ms: u64,
+
+    /// Shell command to run after each purchase, with the item details in
+    /// FUGC_ITEM_* environment variables
+    #[arg(long)]
+    on_purchase: Option<String>,
+
+    /// Extra header sent with every request, e.g. `--header "Accept-Language: en-US"`.
+    /// Can be repeated
+    #[arg(long = "header", value_name = "NAME: VALUE")]
+    headers: Vec<CustomHeader>,
+
+    /// Locale of the item names and availability in search results and
+    /// details, e.g. `en-us`. Defaults to the locale of the account
+    #[arg(long, value_parser = parse_locale)]
+    locale: Option<String>,
+
+    /// Only speak HTTP/1.1, for proxies that break HTTP/2 connections
+    #[arg(long)]
+    http1_only: bool,
+
+    /// Most purchase attempts per minute, retries included, shared by every
+    /// account of the run
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    rate: Option<u32>,
+
+    /// Ratelimits in a row after which each further one waits longer than
+    /// the usual cooldown
+    #[arg(long, default_value_t = 3)]
+    escalate_cooldown_after: u32,
+
+    /// Longest cooldown an escalating ratelimit waits, in seconds
+    #[arg(long, default_value_t = 900)]
+    max_cooldown: u64,
+
+    /// Ownership checks of a page run at the same time
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    ownership_concurrency: u32,
+
+    /// Largest search or item details response read, e.g. `512K` or `16M`.
+    /// Larger ones are refused instead of being buffered
+    #[arg(long, default_value = "16M", value_parser = body::parse_size)]
+    max_body_size: u64,
+
+    /// Times to try reaching Roblox again at startup when the connection or
+    /// DNS lookup fails, before giving up
+    #[arg(long, default_value_t = 3)]
+    network_retries: u32,
+
+    /// Save the search position after each page and continue from it on the
+    /// next run with the same filters
+    #[arg(long)]
+    resume: bool,
+
+    /// Directory for the checkpoints of --resume
+    #[arg(long, default_value = ".fugc-state")]
+    state_dir: PathBuf,
+
+    /// Append every purchase event to this file as newline-delimited JSON
+    #[arg(long)]
+    event_log: Option<PathBuf>,
+
+    /// Treat the items the event log records as purchased by this account
+    /// as owned, without checking
+    #[arg(long, requires = "event_log")]
+    skip_reported: bool,
+
+    /// Write the resolved options and the summary of the run to this JSON
+    /// file once it finishes, with credentials redacted
+    #[arg(long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
+
+    /// Measure the highest search and ownership request rate the account
+    /// sustains without ratelimits instead of purchasing
+    #[arg(long)]
+    benchmark: bool,
+
+    /// How the outcome of each purchase is shown. Without a terminal the
+    /// table is written as tab separated lines
+    #[arg(long, value_enum, default_value_t = Format::Lines)]
+    format: Format,
+
+    /// API to buy collectibles with. Other items are always bought through
+    /// the economy endpoint
+    #[arg(long, value_enum, default_value_t = PurchaseApi::Legacy)]
+    purchase_api: PurchaseApi,
+
+    /// Print additional details about the run
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Leave out the page progress, the lines of items that didn't fail and
+    /// the ratelimit countdown. Warnings and the summary are still printed
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Only print and log these kinds of item events, e.g.
+    /// `purchased,failed`. Every kind is shown when not set
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "EVENTS")]
+    show: Vec<EventKind>,
+
+    /// Log the duration of each request and summarize latencies at the end
+    #[arg(long)]
+    timings: bool,
+
+    /// Base URL of the catalog API
+    #[arg(long, env = "FUGC_CATALOG_BASE_URL", default_value = CATALOG_BASE_URL, hide = true)]
+    catalog_base_url: String,
+
+    /// Base URL of the users API
+    #[arg(long, env = "FUGC_USERS_BASE_URL", default_value = USERS_BASE_URL, hide = true)]
+    users_base_url: String,
+
+    /// Base URL of the inventory API
+    #[arg(long, env = "FUGC_INVENTORY_BASE_URL", default_value = INVENTORY_BASE_URL, hide = true)]
+    inventory_base_url: String,
+
+    /// Base URL of the economy API
+    #[arg(long, env = "FUGC_ECONOMY_BASE_URL", default_value = ECONOMY_BASE_URL, hide = true)]
+    economy_base_url: String,
+
+    /// Base URL of the collectibles marketplace APIs
+    #[arg(long, env = "FUGC_APIS_BASE_URL", default_value = APIS_BASE_URL, hide = true)]
+    apis_base_url: String,
+
+    /// Base URL of the website, used to scrape the CSRF token
+    #[arg(long, env = "FUGC_WWW_BASE_URL", default_value = WWW_BASE_URL, hide = true)]
+    www_base_url: String,
+
+    /// Base URL of the premium features API
+    #[arg(long, env = "FUGC_PREMIUM_FEATURES_BASE_URL", default_value = PREMIUM_FEATURES_BASE_URL, hide = true)]
+    premium_features_base_url: String,
+
+    /// Base URL of the groups API
+    #[arg(long, env = "FUGC_GROUPS_BASE_URL", default_value = GROUPS_BASE_URL, hide = true)]
+    groups_base_url: String,
+
+    /// Base URL of the games API
+    #[arg(long, env = "FUGC_GAMES_BASE_URL", default_value = GAMES_BASE_URL, hide = true)]
+    games_base_url: String,
+
+    /// Base URL of the user moderation API, used to check the account state
+    #[arg(long, env = "FUGC_USERMODERATION_BASE_URL", default_value = USERMODERATION_BASE_URL, hide = true)]
+    usermoderation_base_url: String,
+
+    /// Delay between purchases in milliseconds
+    #[arg(long, default_value_t = 1000, hide = true)]
+    purchase_interval_ms: u64,
+
+    /// Wait after a ratelimit in milliseconds
+    #[arg(long, default_value_t = 65_000, hide = true)]
+    ratelimit_interval_ms: u64,
+}
+
+fn build_client(args: &Args) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut headers = HeaderMap::new();
+
+    for header in args.headers.iter() {
+        // Requests set these themselves, which takes precedence
+        if header.name == COOKIE || header.name.as_str() == CSRF_TOKEN_HEADER {
+            println!(
+                "{} --header {} is set by each request and will be ignored",
+                "Warning".bold().yellow(),
+                header.name
+            );
+        }
+
+        headers.insert(header.name.clone(), header.value.clone());
+    }
+
+    let mut builder = Client::builder().default_headers(headers);
+
+    if args.http1_only {
+        builder = builder.http1_only();
+    }
+
+    Ok(builder.build()?)
+}
+
+fn get_cookie(args: &Args) -> String {
+    format!(
+        ".ROBLOSECURITY={}",
+        args.auth.as_deref().unwrap_or_default()
+    )
+}
+
+fn add_bound_auth_token(request: RequestBuilder, args: &Args) -> RequestBuilder {
+    match &args.bound_auth_token {
+        Some(bound_auth_token) => request.header(BOUND_AUTH_TOKEN_HEADER, bound_auth_token),
+        None => request,
+    }
+}
+
+/// Without --locale, names come in the locale of the account
+fn add_locale(request: RequestBuilder, args: &Args) -> RequestBuilder {
+    match &args.locale {
+        Some(locale) => request.header(ACCEPT_LANGUAGE, locale),
+        None => request,
+    }
+}
+
+fn is_numeric_id(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|character| character.is_ascii_digit())
+}
+
+/// Numeric ids from website URLs are sent as `categoryId`/`subcategoryId`,
+/// names as `category`/`subcategory`
+fn get_category_query(args: &Args) -> String {
+    [
+        ("category", &args.category),
+        ("subcategory", &args.subcategory),
+    ]
+    .into_iter()
+    .map(|(parameter, value)| match value {
+        Some(value) if is_numeric_id(value) => format!("{}Id={}", parameter, value),
+        Some(value) => format!("{}={}", parameter, value),
+        None => format!("{}=", parameter),
+    })
+    .collect::<Vec<String>>()
+    .join("&")
+}
+
+fn validate_category(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    if let (Some(category), Some(subcategory)) = (&args.category, &args.subcategory) {
+        if is_numeric_id(category) != is_numeric_id(subcategory) {
+            return Err(
+                "--category and --subcategory must both be names or both be numeric ids".into(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_source(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    match args.source {
+        Source::Gamepasses if args.universe_id.is_none() => {
+            Err("--source gamepasses needs a --universe-id".into())
+        }
+        Source::Gamepasses
+            if args.favorites.is_some()
+                || args.ids_file.is_some()
+                || args.creator_store.is_some() =>
+        {
+            Err("--favorites, --ids-file and --creator-store only apply to --source catalog".into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn get_search_url(args: &Args, next_page_cursor: &Option<String>, page_size: usize) -> String {
+    let creator_query = match args.search_slice {
+        Some(creator_type) => format!("&creatorType={}", creator_type.get_api_name()),
+        None => String::new(),
+    };
+
+    format!(
+        "{}/v2/search/items/details?{}{}&maxPrice={}&includeNotForSale={}&limit={}&cursor={}",
+        args.catalog_base_url,
+        get_category_query(args),
+        creator_query,
+        args.max_price,
+        args.include_not_for_sale,
+        page_size,
+        next_page_cursor.clone().unwrap_or("".to_string())
+    )
+}
+
+/// The search hands out no more cursors past a certain depth, even when
+/// full pages are left. Searching the items of each creator type on its own
+/// reaches that depth again for each of them, which only helps as long as
+/// neither half is past it too. Items of the full search come up again and
+/// are skipped as owned, or as seen with --no-owned-check
+fn get_next_search_slice(args: &Args, current: Option<CreatorType>) -> Option<CreatorType> {
+    if args.source != Source::Catalog
+        || args.favorites.is_some()
+        || args.ids_file.is_some()
+        || args.creator_store.is_some()
+        || args.creator_types.len() < 2
+    {
+        return None;
+    }
+
+    match current {
+        None => args.creator_types.first().copied(),
+        Some(current) => args
+            .creator_types
+            .iter()
+            .skip_while(|creator_type| **creator_type != current)
+            .nth(1)
+            .copied(),
+    }
+}
+
+async fn get_search_page(
+    client: &Client,
+    args: &Args,
+    timings: &Timings,
+    next_page_cursor: &Option<String>,
+) -> Result<MarketplaceQueryResponse, SendError> {
+    let mut attempt = 1;
+    let mut page_size = args.max_page_size;
+
+    loop {
+        let started_at = Instant::now();
+        let http_response = add_locale(
+            client.get(get_search_url(args, next_page_cursor, page_size)),
+            args,
+        )
+        .send()
+        .await?;
+        let status = http_response.status();
+        let body = body::read_body(http_response, args.max_body_size).await;
+        timings.record("Search", started_at);
+
+        let body = match body {
+            Ok(body) => body,
+            Err(error) => {
+                if page_size > args.min_page_size {
+                    page_size = shrink_page_size(args, page_size, &error);
+                    continue;
+                }
+
+                return Err(format!("Search failed, the {}", error).into());
+            }
+        };
+
+        // Maintenance pages and Cloudflare challenges aren't JSON at all
+        let failure = match serde_json::from_slice::<MarketplaceQueryResponse>(&body) {
+            Ok(mut response) => {
+                response.status = status.as_u16();
+                response.page_size = page_size;
+
+                // A missing `data` field means the search itself failed, while
+                // an empty page can still be followed by more pages
+                if response.errors.is_none() && response.data.is_some() {
+                    return Ok(response);
+                }
+
+                let error_codes = response
+                    .errors
+                    .iter()
+                    .flatten()
+                    .map(|error| error.code.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                format!("status {} (error codes: {})", response.status, error_codes)
+            }
+            // Large pages are sometimes cut short under load, smaller ones
+            // tend to get through
+            Err(error) if status.is_success() && page_size > args.min_page_size => {
+                page_size = shrink_page_size(args, page_size, &error.to_string());
+                continue;
+            }
+            Err(error) => format!(
+                "status {} and an unreadable body ({}): {}",
+                status.as_u16(),
+                error,
+                get_body_snippet(&String::from_utf8_lossy(&body))
+            ),
+        };
+
+        if attempt >= SEARCH_ATTEMPTS {
+            return Err(format!("Search failed with {}", failure).into());
+        }
+
+        println!(
+            "{} with {}, retrying in {} seconds..",
+            "Search failed".red(),
+            failure,
+            SEARCH_RETRY_INTERVAL.as_secs()
+        );
+        tokio::time::sleep(SEARCH_RETRY_INTERVAL).await;
+        attempt += 1;
+    }
+}
+
+/// The page at `next_page_cursor` of whichever source the items come from
+async fn get_page(
+    session: &Session,
+    next_page_cursor: &Option<String>,
+) -> Result<MarketplaceQueryResponse, Box<dyn std::error::Error>> {
+    let args = &session.args;
+
+    match (
+        args.source,
+        args.favorites,
+        args.universe_id,
+        &args.ids_file,
+        args.creator_store,
+    ) {
+        (Source::Gamepasses, _, Some(universe_id), _, _) => {
+            gamepasses::get_gamepasses_page(session, universe_id, next_page_cursor).await
+        }
+        (_, Some(asset_type_id), _, _, _) => {
+            favorites::get_favorites_page(session, asset_type_id, next_page_cursor).await
+        }
+        (_, _, _, Some(ids_file), _) => {
+            ids_file::get_ids_page(session, ids_file, next_page_cursor).await
+        }
+        (_, _, _, _, Some(creator_id)) => {
+            creator_store::get_store_page(
+                session,
+                creator_id,
+                args.creator_store_type,
+                next_page_cursor,
+            )
+            .await
+        }
+        _ => get_search_page(&session.client, args, &session.timings, next_page_cursor)
+            .await
+            .map_err(|error| error as Box<dyn std::error::Error>),
+    }
+}
+
+fn shrink_page_size(args: &Args, page_size: usize, reason: &str) -> usize {
+    let page_size = (page_size / 2).max(args.min_page_size);
+
+    println!(
+        "{} ({}), retrying with {} items per page..",
+        "Search page was malformed".red(),
+        reason,
+        page_size
+    );
+
+    page_size
+}
+
+/// Item names are chosen by their creators and printed as is, so control
+/// characters (escape sequences, bidi overrides) are dropped to keep them
+/// from rewriting the terminal or reordering the rest of the line
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .filter(|character| {
+            !character.is_control()
+                && !matches!(
+                    character,
+                    '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+                )
+        })
+        .collect()
+}
+
+fn deserialize_item_name<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    Ok(sanitize_name(&String::deserialize(deserializer)?))
+}
+
+/// An unreadable deadline is treated as none rather than failing the page
+fn deserialize_off_sale_deadline<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Timestamp>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.and_then(|deadline| deadline.parse().ok()))
+}
+
+fn deserialize_transaction_id<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::String(transaction_id)) => Some(transaction_id),
+            Some(serde_json::Value::Number(transaction_id)) => Some(transaction_id.to_string()),
+            _ => None,
+        },
+    )
+}
+
+fn get_body_snippet(body: &str) -> String {
+    let body = body.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if body.chars().count() > BODY_SNIPPET_LENGTH {
+        format!(
+            "{}..",
+            body.chars().take(BODY_SNIPPET_LENGTH).collect::<String>()
+        )
+    } else {
+        body
+    }
+}
+
+/// Number of pages fetched ahead of the one being purchased, leaving room
+/// for that page within --max-concurrent-pages
+fn get_prefetch_buffer_size(args: &Args) -> usize {
+    let max_buffered_pages = args.max_concurrent_pages.saturating_sub(1) as usize;
+    args.prefetch_pages.min(max_buffered_pages)
+}
+
+fn spawn_page_prefetcher(
+    client: Client,
+    args: Args,
+    timings: Arc<Timings>,
+    mut next_page_cursor: Option<String>,
+) -> mpsc::Receiver<Result<MarketplaceQueryResponse, SendError>> {
+    let (sender, receiver) = mpsc::channel(get_prefetch_buffer_size(&args));
+
+    tokio::spawn(async move {
+        // Reserve a slot before fetching so no more than the buffer size
+        // pages are ever waiting to be processed
+        while let Ok(permit) = sender.reserve().await {
+            let response = get_search_page(&client, &args, &timings, &next_page_cursor).await;
+
+            let is_last_page = match &response {
+                Ok(page) => page.next_page_cursor.is_none(),
+                Err(_) => true,
+            };
+
+            if let Ok(page) = &response {
+                next_page_cursor = page.next_page_cursor.clone();
+            }
+
+            permit.send(response);
+
+            if is_last_page {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+async fn get_authenticated_user(
+    client: &Client,
+    args: &Args,
+) -> Result<AuthenticatedUserResponse, Error> {
+    client
+        .get(format!("{}/v1/users/authenticated", args.users_base_url))
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?
+        .json::<AuthenticatedUserResponse>()
+        .await
+}
+
+async fn authenticated_user_owns_bundle(
+    session: &Session,
+    item: &MarketplaceQueryResponseItem,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let started_at = Instant::now();
+    let user_owns_bundle = session
+        .client
+        .get(format!(
+            "{}/v1/users/{}/items/{}/{}/is-owned",
+            session.args.inventory_base_url, session.user_id, item.item_type, item.id
+        ))
+        .header("Cookie", get_cookie(&session.args))
+        .send()
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+    session.timings.record("Ownership", started_at);
+
+    user_owns_bundle.as_bool().ok_or_else(|| {
+        format!(
+            "Unexpected ownership response for {}: {}",
+            item.id, user_owns_bundle
+        )
+        .into()
+    })
+}
+
+async fn scrape_csrf_token(
+    client: &Client,
+    args: &Args,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let body = client
+        .get(format!("{}/home", args.www_base_url))
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?
+        .text()
+        .await?;
+
+    let document = Document::from_read(body.as_bytes()).unwrap();
+
+    for node in document.find(Name("meta")) {
+        if let Some(attr) = node.attr("name") {
+            if attr == "csrf-token" {
+                if let Some(csrf_value) = node.attr("data-token") {
+                    return Ok(csrf_value.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(String::new())
+}
+
+/// A POST without a token is refused with a fresh one in the response
+/// headers. The details request is harmless to send for that
+async fn get_csrf_token_from_header(
+    client: &Client,
+    args: &Args,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let response = client
+        .post(format!(
+            "{}/v1/catalog/items/details",
+            args.catalog_base_url
+        ))
+        .json(&ItemDetailsQuery { items: Vec::new() })
+        .header("Cookie", get_cookie(args))
+        .send()
+        .await?;
+
+    Ok(response
+        .headers()
+        .get(CSRF_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// Scrapes the CSRF token from the home page, falling back to the response
+/// headers, and tries both again with a growing delay before giving up
+async fn get_csrf_token(
+    client: &Client,
+    args: &Args,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut delay = CSRF_RETRY_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        let error = match scrape_csrf_token(client, args).await {
+            Ok(csrf_token) if !csrf_token.is_empty() => return Ok(csrf_token),
+            Ok(_) => "missing from the home page".to_string(),
+            Err(error) => error.to_string(),
+        };
+
+        let error = match get_csrf_token_from_header(client, args).await {
+            Ok(csrf_token) if !csrf_token.is_empty() => return Ok(csrf_token),
+            Ok(_) => format!("{} and the response headers", error),
+            Err(header_error) => format!("{}, {}", error, header_error),
+        };
+
+        if attempt >= CSRF_FETCH_ATTEMPTS {
+            return Err(format!("Failed to get a CSRF token: {}", error).into());
+        }
+
+        if args.verbose {
+            println!(
+                "{}",
+                format!(
+                    "No CSRF token ({}), trying again in {}s",
+                    error,
+                    delay.as_secs()
+                )
+                .truecolor(150, 150, 150)
+            );
+        }
+
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+async fn is_asset_available(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+) -> Result<Availability, Box<dyn std::error::Error>> {
+    if session.reported_ids.contains(&asset.id) {
+        if session.args.verbose {
+            println!(
+                "{}",
+                format!("{} was purchased in an earlier run", asset.name).truecolor(150, 150, 150)
+            );
+        }
+        return Ok(Availability::Owned);
+    }
+
+    if asset.creator_type == "User" && asset.creator_target_id == session.user_id {
+        return Ok(Availability::OwnCreation);
+    }
+
+    if !session.args.no_owned_check {
+        let page_ownership = session
+            .page_ownership
+            .lock()
+            .unwrap()
+            .remove(&(asset.item_type.clone(), asset.id));
+        let owned = match page_ownership {
+            Some(owned) => Ok(owned),
+            None => authenticated_user_owns_bundle(session, asset).await,
+        };
+
+        match owned {
+            Ok(true) => return Ok(Availability::Owned),
+            Ok(false) => {}
+            Err(error) => {
+                if session.args.on_owned_check_error == OwnedCheckErrorAction::Abort {
+                    return Err(error);
+                }
+
+                println!(
+                    "{} Failed to check the ownership of {} ({})",
+                    "Warning".bold().yellow(),
+                    asset.name,
+                    error
+                );
+
+                if session.args.on_owned_check_error == OwnedCheckErrorAction::Skip {
+                    return Ok(Availability::Unknown);
+                }
+            }
+        }
+    }
+
+    if asset.creator_type == "User" && asset.creator_target_id == 1 {
+        return Ok(Availability::CreatedByRoblox);
+    }
+
+    Ok(Availability::Available)
+}
+
+/// Fills `page_ownership` for the items of a page with one request. Items
+/// it has no answer for, or all of them if it fails, are checked one by one
+async fn check_page_ownership(session: &Session, assets: &[MarketplaceQueryResponseItem]) {
+    let page_ownership = if session.args.no_owned_check || assets.is_empty() {
+        HashMap::new()
+    } else {
+        ownership::get_batch_ownership(session, assets)
+            .await
+            .unwrap_or_else(|error| {
+                if session.args.verbose {
+                    println!(
+                        "{}",
+                        format!("{}, checking items one by one", error).truecolor(150, 150, 150)
+                    );
+                }
+                HashMap::new()
+            })
+    };
+
+    *session.page_ownership.lock().unwrap() = page_ownership;
+}
+
+/// Collectibles can no longer be bought through the economy endpoint, so
+/// they are routed to the marketplace once their product id is resolved
+async fn resolve_purchase_route(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+) -> Result<PurchaseRoute, Box<dyn std::error::Error>> {
+    let Some(collectible_item_id) = &asset.collectible_item_id else {
+        return Ok(PurchaseRoute::Economy);
+    };
+
+    let key = (asset.item_type.clone(), asset.id);
+
+    if let Some(route) = session.purchase_routes.lock().unwrap().get(&key) {
+        return Ok(route.clone());
+    }
+
+    let collectible_product_id = get_collectible_product_id(
+        &session.client,
+        &session.args,
+        &session.get_csrf_token(),
+        collectible_item_id,
+    )
+    .await?;
+    let route = PurchaseRoute::Collectible {
+        collectible_product_id,
+    };
+
+    session
+        .purchase_routes
+        .lock()
+        .unwrap()
+        .insert(key, route.clone());
+
+    Ok(route)
+}
+
+async fn purchase_asset(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+    route: &PurchaseRoute,
+) -> Result<Response, Error> {
+    let started_at = Instant::now();
+    let response = match route {
+        PurchaseRoute::Economy => {
+            let request = session.client.post(format!(
+                "{}/v1/purchases/products/{}",
+                session.args.economy_base_url, asset.product_id
+            ));
+
+            add_bound_auth_token(request, &session.args)
+                .body(AssetPurchaseQuery {
+                    expected_currency: 1,
+                    expected_price: asset.price.unwrap_or(0),
+                    expected_seller_id: asset.creator_target_id,
+                    expected_purchaser_id: session.args.use_group_funds,
+                    expected_purchaser_type: session
+                        .args
+                        .use_group_funds
+                        .map(|_| "Group".to_string()),
+                })
+                .header("Content-Type", "application/json; charset=utf-8")
+                .header("Cookie", get_cookie(&session.args))
+                .header("X-CSRF-TOKEN", session.get_csrf_token())
+                .send()
+                .await
+        }
+        PurchaseRoute::Collectible {
+            collectible_product_id,
+        } => {
+            purchase_collectible(
+                &session.client,
+                asset,
+                &session.args,
+                &session.get_csrf_token(),
+                CollectiblePurchaseQuery {
+                    collectible_item_id: asset.collectible_item_id.clone().unwrap_or_default(),
+                    collectible_product_id: collectible_product_id.clone(),
+                    expected_currency: 1,
+                    expected_price: asset.price.unwrap_or(0),
+                    expected_purchaser_id: session.get_purchaser_id().to_string(),
+                    expected_purchaser_type: session.get_purchaser_type().to_string(),
+                    expected_seller_id: asset.creator_target_id,
+                    expected_seller_type: asset.creator_type.clone(),
+                    idempotency_key: generate_idempotency_key(),
+                    collectible_item_instance_id: None,
+                },
+            )
+            .await
+        }
+    };
+    session.timings.record("Purchase", started_at);
+
+    response
+}
+
+/// A verification step, e.g. a captcha, demanded instead of the purchase
+struct Challenge {
+    challenge_type: String,
+    id: Option<String>,
+    metadata: Option<String>,
+}
+
+fn get_challenge(response: &Response) -> Option<Challenge> {
+    let get_header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+
+    Some(Challenge {
+        challenge_type: get_header(CHALLENGE_TYPE_HEADER)?.to_lowercase(),
+        id: get_header(CHALLENGE_ID_HEADER),
+        metadata: get_header(CHALLENGE_METADATA_HEADER),
+    })
+}
+
+fn is_bound_auth_token_error(error: &ApiError) -> bool {
+    error.message.as_deref().is_some_and(|message| {
+        let message = message.to_lowercase();
+        message.contains("bound auth token") || message.contains("boundauthtoken")
+    })
+}
+
+fn run_purchase_hook(command: &str, asset: &MarketplaceQueryResponseItem) {
+    let mut hook = if cfg!(windows) {
+        let mut hook = process::Command::new("cmd");
+        hook.arg("/C").arg(command);
+        hook
+    } else {
+        let mut hook = process::Command::new("sh");
+        hook.arg("-c").arg(command);
+        hook
+    };
+
+    let spawned = hook
+        .env("FUGC_ITEM_ID", asset.id.to_string())
+        .env("FUGC_ITEM_NAME", &asset.name)
+        .env("FUGC_ITEM_TYPE", &asset.item_type)
+        .env("FUGC_PRODUCT_ID", asset.product_id.to_string())
+        .env("FUGC_CREATOR_ID", asset.creator_target_id.to_string())
+        .env("FUGC_CREATOR_TYPE", &asset.creator_type)
+        .spawn();
+
+    if let Err(error) = spawned {
+        println!(
+            "{} {}",
+            "Failed to run the purchase hook:".bold().yellow(),
+            error
+        );
+    }
+}
+
+/// Buys the cheapest resale listing of a sold out collectible if it fits in
+/// the budget. Listings change quickly, so a failure isn't retried
+async fn attempt_resale_purchase(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+    collectible_item_id: &str,
+    budget: u32,
+) -> Result<PurchaseStatus, Box<dyn std::error::Error>> {
+    let asset_link = asset
+        .name
+        .hyperlink(format!("https://www.roblox.com/catalog/{}", asset.id));
+
+    let resale =
+        match get_cheapest_resale(&session.client, &session.args, collectible_item_id).await? {
+            Some(resale) if resale.price <= budget && session.can_afford(resale.price) => resale,
+            _ => {
+                session.report(
+                    EventKind::Skipped,
+                    format_args!(
+                        "{} is sold out with no resale within {} Robux",
+                        asset_link.truecolor(150, 150, 150),
+                        budget
+                    ),
+                );
+                return Ok(PurchaseStatus::Skipped);
+            }
+        };
+
+    let started_at = Instant::now();
+    let purchase_response = purchase_collectible(
+        &session.client,
+        asset,
+        &session.args,
+        &session.get_csrf_token(),
+        CollectiblePurchaseQuery {
+            collectible_item_id: collectible_item_id.to_string(),
+            collectible_product_id: resale.collectible_product_id,
+            expected_currency: 1,
+            expected_price: resale.price,
+            expected_purchaser_id: session.get_purchaser_id().to_string(),
+            expected_purchaser_type: session.get_purchaser_type().to_string(),
+            expected_seller_id: resale.seller.seller_id,
+            expected_seller_type: resale.seller.seller_type,
+            idempotency_key: generate_idempotency_key(),
+            collectible_item_instance_id: Some(resale.collectible_item_instance_id),
+        },
+    )
+    .await?;
+    session.timings.record("Purchase", started_at);
+
+    if get_collectible_purchase_errors(purchase_response, &session.args)
+        .await?
+        .is_some()
+    {
+        session.report(
+            EventKind::Failed,
+            format_args!(
+                "{} resale of {}",
+                "Failed to purchase".bold().red(),
+                asset_link
+            ),
+        );
+        session.set_purchase_error(None, "resale purchase failed");
+        return Ok(PurchaseStatus::Failed);
+    }
+
+    session.record_spend(resale.price)?;
+
+    session.report(
+        EventKind::Purchased,
+        format_args!(
+            "{} resale of {} for {} Robux",
+            "Purchased".bold().green(),
+            asset_link,
+            resale.price
+        ),
+    );
+
+    Ok(PurchaseStatus::Purchased)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ItemDetailsQueryItem {
+    item_type: String,
+    id: u64,
+}
+
+#[derive(Serialize)]
+struct ItemDetailsQuery {
+    items: Vec<ItemDetailsQueryItem>,
+}
+
+#[derive(Deserialize)]
+struct ItemDetailsResponse {
+    data: Vec<MarketplaceQueryResponseItem>,
+}
+
+async fn get_item_details(
+    session: &Session,
+    items: Vec<ItemDetailsQueryItem>,
+) -> Result<Vec<MarketplaceQueryResponseItem>, Box<dyn std::error::Error>> {
+    let request = session.client.post(format!(
+        "{}/v1/catalog/items/details",
+        session.args.catalog_base_url
+    ));
+    let response = add_locale(request, &session.args)
+        .json(&ItemDetailsQuery { items })
+        .header("Cookie", get_cookie(&session.args))
+        .header("X-CSRF-TOKEN", session.get_csrf_token())
+        .send()
+        .await?;
+    let status = response.status();
+    let body = body::read_body(response, session.args.max_body_size)
+        .await
+        .map_err(|error| format!("Item details failed, the {}", error))?;
+    let details = serde_json::from_slice::<ItemDetailsResponse>(&body).map_err(|error| {
+        format!(
+            "Item details failed with status {} and an unreadable body ({}): {}",
+            status.as_u16(),
+            error,
+            get_body_snippet(&String::from_utf8_lossy(&body))
+        )
+    })?;
+
+    Ok(details.data)
+}
+
+/// Reads the current details of an item. Moderated and deleted items are
+/// left out of the details, so they come back as `None`
+async fn get_current_details(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+) -> Result<Option<MarketplaceQueryResponseItem>, Box<dyn std::error::Error>> {
+    let details = get_item_details(
+        session,
+        vec![ItemDetailsQueryItem {
+            item_type: asset.item_type.clone(),
+            id: asset.id,
+        }],
+    )
+    .await?;
+
+    Ok(details.into_iter().find(|item| item.id == asset.id))
+}
+
+/// Re-reads the price after the purchase was refused because it changed,
+/// retrying with the new price unless it exceeds --max-price
+async fn retry_with_current_price(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+    interval: Duration,
+    ratelimit_interval: Duration,
+) -> Result<PurchaseStatus, Box<dyn std::error::Error>> {
+    let asset_link = asset.name.truecolor(150, 150, 150);
+
+    let Some(details) = get_current_details(session, asset).await? else {
+        session.report(
+            EventKind::Moderated,
+            format_args!("{} is unavailable (moderated)", asset_link),
+        );
+        return Ok(PurchaseStatus::Moderated);
+    };
+
+    let price = match details.price {
+        Some(price) => price,
+        None => {
+            session.report(
+                EventKind::SaleEnded,
+                format_args!("{} is no longer for sale", asset_link),
+            );
+            return Ok(PurchaseStatus::SaleEnded);
+        }
+    };
+
+    if price > session.args.max_price {
+        session.report(
+            EventKind::Skipped,
+            format_args!(
+                "{} now costs {} Robux, more than --max-price",
+                asset_link, price
+            ),
+        );
+        return Ok(PurchaseStatus::Skipped);
+    }
+
+    // The details disagree with the purchase endpoint, retrying won't help
+    if asset.price == Some(price) {
+        session.report(
+            EventKind::Failed,
+            format_args!(
+                "{} {} (price changed but still listed at {} Robux)",
+                "Failed to purchase".bold().red(),
+                asset.name,
+                price
+            ),
+        );
+        session.set_purchase_error(
+            None,
+            format!("price changed but still listed at {} Robux", price),
+        );
+        return Ok(PurchaseStatus::Failed);
+    }
+
+    if session.args.verbose {
+        println!(
+            "{}",
+            format!("{} changed price to {} Robux, retrying", asset.name, price)
+                .truecolor(150, 150, 150)
+        );
+    }
+
+    let mut asset = asset.clone();
+    asset.price = Some(price);
+
+    session.record_retry()?;
+    try_purchase(session, &asset, interval, ratelimit_interval).await
+}
+
+/// Reads the price of a paid item again for --price-tolerance, returning the
+/// current price if it is still within the tolerance
+async fn confirm_price(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+    tolerance: u32,
+) -> Result<Result<u32, PurchaseStatus>, Box<dyn std::error::Error>> {
+    let asset_link = asset.name.truecolor(150, 150, 150);
+    let listed_price = asset.price.unwrap_or(0);
+
+    let Some(details) = get_current_details(session, asset).await? else {
+        session.report(
+            EventKind::Moderated,
+            format_args!("{} is unavailable (moderated)", asset_link),
+        );
+        return Ok(Err(PurchaseStatus::Moderated));
+    };
+
+    let Some(price) = details.price else {
+        session.report(
+            EventKind::SaleEnded,
+            format_args!("{} is no longer for sale", asset_link),
+        );
+        return Ok(Err(PurchaseStatus::SaleEnded));
+    };
+
+    if price > listed_price.saturating_add(tolerance) || price > session.args.max_price {
+        session.report(
+            EventKind::Skipped,
+            format_args!(
+                "{} skipped, its price went from {} to {} Robux",
+                asset_link, listed_price, price
+            ),
+        );
+        return Ok(Err(PurchaseStatus::Skipped));
+    }
+
+    if price != listed_price && session.args.verbose {
+        println!(
+            "{}",
+            format!(
+                "{} changed price from {} to {} Robux, within --price-tolerance",
+                asset.name, listed_price, price
+            )
+            .truecolor(150, 150, 150)
+        );
+    }
+
+    Ok(Ok(price))
+}
+
+/// Sleeps through a ratelimit, counting down in place when attached to a
+/// terminal so the wait doesn't look like a hang
+async fn wait_for_ratelimit(ratelimit_interval: Duration) {
+    let mut stdout = io::stdout();
+
+    if !stdout.is_terminal() {
+        println!(
+            "{}",
+            format!(
+                "Ratelimit reached. Waiting {} seconds..",
+                ratelimit_interval.as_secs()
+            )
+            .red()
+        );
+        tokio::time::sleep(ratelimit_interval).await;
+        return;
+    }
+
+    count_down(&mut stdout, ratelimit_interval).await;
+}
+
+/// Sleeps a second at a time, redrawing the time left on the current line
+async fn count_down(out: &mut impl Write, duration: Duration) {
+    let mut remaining = duration;
+
+    while !remaining.is_zero() {
+        let _ = write!(
+            out,
+            "\r\x1b[2K{}",
+            format!(
+                "Ratelimit reached. Resuming in {}s..",
+                remaining.as_secs_f64().ceil() as u64
+            )
+            .red()
+        );
+        let _ = out.flush();
+
+        let step = remaining.min(Duration::from_secs(1));
+        tokio::time::sleep(step).await;
+        remaining -= step;
+    }
+
+    let _ = write!(out, "\r\x1b[2K");
+    let _ = out.flush();
+}
+
+/// Purchases an item, retrying as the error codes call for, and sums up how
+/// it went
+async fn attempt_purchase(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+    interval: Duration,
+    ratelimit_interval: Duration,
+) -> Result<PurchaseOutcome, Box<dyn std::error::Error>> {
+    let retries = session.retries.load(Ordering::Relaxed);
+    let spent = session.spent.load(Ordering::Relaxed);
+    *session.purchase_error.lock().unwrap() = None;
+    session.moderation_checked.store(false, Ordering::Relaxed);
+
+    let status = try_purchase(session, asset, interval, ratelimit_interval).await?;
+    // Errors a retry got past don't matter anymore
+    let error = session
+        .purchase_error
+        .lock()
+        .unwrap()
+        .take()
+        .filter(|_| !matches!(status, PurchaseStatus::Purchased));
+
+    Ok(PurchaseOutcome {
+        item: ItemSummary::from(asset),
+        status,
+        attempts: session.retries.load(Ordering::Relaxed) - retries + 1,
+        spent: (session.spent.load(Ordering::Relaxed) - spent) as u32,
+        error,
+    })
+}
+
+#[async_recursion(?Send)]
+async fn try_purchase(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+    interval: Duration,
+    ratelimit_interval: Duration,
+) -> Result<PurchaseStatus, Box<dyn std::error::Error>> {
+    let asset_link = asset
+        .name
+        .hyperlink(format!("https://www.roblox.com/bundles/{}", asset.id));
+
+    if asset.price.is_none() {
+        session.report(
+            EventKind::Skipped,
+            format_args!("{} has no price", asset_link.truecolor(150, 150, 150)),
+        );
+        return Ok(PurchaseStatus::Skipped);
+    }
+
+    if asset.price_status.as_deref() == Some(OFF_SALE_PRICE_STATUS) {
+        session.report(
+            EventKind::SaleEnded,
+            format_args!("{} is off sale", asset_link.truecolor(150, 150, 150)),
+        );
+        return Ok(PurchaseStatus::SaleEnded);
+    }
+
+    let confirmed_asset;
+    let asset = match session.args.price_tolerance {
+        Some(tolerance) if asset.price.is_some_and(|price| price > 0) => {
+            match confirm_price(session, asset, tolerance).await? {
+                Ok(price) => {
+                    confirmed_asset = MarketplaceQueryResponseItem {
+                        price: Some(price),
+                        ..asset.clone()
+                    };
+                    &confirmed_asset
+                }
+                Err(status) => return Ok(status),
+            }
+        }
+        _ => asset,
+    };
+
+    let price = asset.price.unwrap_or(0);
+
+    if !session.can_afford(price) {
+        session.report(
+            EventKind::Skipped,
+            format_args!(
+                "{} skipped, buying it would exceed --max-spend",
+                asset_link.truecolor(150, 150, 150)
+            ),
+        );
+        return Ok(PurchaseStatus::Skipped);
+    }
+
+    if let Some(rate_limiter) = &session.rate_limiter {
+        rate_limiter.acquire().await;
+    }
+
+    let args = &session.args;
+
+    if let (Some(collectible_item_id), Some(budget)) =
+        (&asset.collectible_item_id, args.allow_resale_up_to)
+    {
+        if asset.units_available_for_consumption == Some(0) {
+            return attempt_resale_purchase(session, asset, collectible_item_id, budget).await;
+        }
+    }
+
+    let route = resolve_purchase_route(session, asset).await?;
+
+    let purchase_timeout = Duration::from_secs(args.purchase_timeout_secs);
+    let purchase_result = match tokio::time::timeout(
+        purchase_timeout,
+        purchase_asset(session, asset, &route),
+    )
+    .await
+    {
+        Ok(purchase_result) => purchase_result.map_err(|error| error.to_string()),
+        Err(_) => Err(format!("timed out after {}s", purchase_timeout.as_secs())),
+    };
+
+    let purchase_response = match purchase_result {
+        Ok(purchase_response) => purchase_response,
+        Err(reason) => {
+            session.set_purchase_error(None, &reason);
+            session.report(
+                EventKind::Failed,
+                format_args!(
+                    "{} {} ({})",
+                    "Failed to purchase".bold().red(),
+                    asset_link,
+                    reason
+                ),
+            );
+
+            if args.fail_fast {
+                return Err(
+                    Aborted(format!("Failed to purchase {}: {}", asset.name, reason)).into(),
+                );
+            }
+
+            session.record_retry()?;
+            return try_purchase(session, asset, interval, ratelimit_interval).await;
+        }
+    };
+
+    // Retrying a challenged purchase only makes the challenge stricter
+    if let Some(challenge) = get_challenge(&purchase_response) {
+        if challenge.challenge_type == TWO_STEP_VERIFICATION_CHALLENGE {
+            println!(
+                "{} {}",
+                "Two-step verification is required to purchase".bold().red(),
+                asset_link
+            );
+            println!(
+                "Disable two-step verification for purchases in your account's security settings and try again"
+            );
+            return Err(
+                Aborted("Two-step verification is required for purchases".to_string()).into(),
+            );
+        }
+
+        println!(
+            "{} {} ({} challenge)",
+            "Account challenged while purchasing".bold().red(),
+            asset_link,
+            challenge.challenge_type
+        );
+        if let Some(id) = &challenge.id {
+            println!("  Challenge id: {}", id);
+        }
+        if let Some(metadata) = &challenge.metadata {
+            println!("  Challenge metadata: {}", metadata);
+        }
+        println!("Complete the verification on the website, then run again");
+
+        return Err(Aborted(format!(
+            "Account challenged ({}), manual verification needed",
+            challenge.challenge_type
+        ))
+        .into());
+    }
+
+    // Counted before the CSRF refresh, refused purchases often come with a token
+    if let Some(throttle) = &session.throttle {
+        let status = purchase_response.status();
+
+        if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+            let delay = throttle.on_throttled();
+            println!(
+                "{}",
+                format!(
+                    "Purchase refused with status {}, slowing down to {}ms between purchases",
+                    status.as_u16(),
+                    delay.as_millis()
+                )
+                .yellow()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    // An expired token is refused with a fresh one in the response headers
+    if purchase_response.status() == StatusCode::FORBIDDEN {
+        if let Some(csrf_token) = purchase_response
+            .headers()
+            .get(CSRF_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            if args.verbose {
+                println!("{}", "Refreshed the CSRF token".truecolor(150, 150, 150));
+            }
+
+            session.set_csrf_token(csrf_token.to_string());
+            session.record_retry()?;
+            return try_purchase(session, asset, interval, ratelimit_interval).await;
+        }
+    }
+
+    // Deleted items can't be purchased anymore, retrying is pointless
+    if purchase_response.status() == StatusCode::NOT_FOUND {
+        session.report(
+            EventKind::NotFound,
+            format_args!("{} no longer exists", asset_link.truecolor(150, 150, 150)),
+        );
+        return Ok(PurchaseStatus::NotFound);
+    }
+
+    let mut transaction_id = None;
+    let errors = match route {
+        PurchaseRoute::Economy => {
+            let status = purchase_response.status();
+            let purchase_body = match purchase_response.json::<AssetPurchaseResponse>().await {
+                Ok(purchase_body) if status != StatusCode::TOO_MANY_REQUESTS => purchase_body,
+                Err(error)
+                    if status != StatusCode::TOO_MANY_REQUESTS
+                        && status != StatusCode::FORBIDDEN =>
+                {
+                    return Err(error.into())
+                }
+                // A 429, or a 403 without a readable body, is a ratelimit
+                // whatever it says, like for collectibles
+                _ => AssetPurchaseResponse {
+                    errors: Some(vec![ApiError {
+                        code: RATELIMIT_ERROR_CODE,
+                        message: None,
+                    }]),
+                    reason: None,
+                    transaction_id: None,
+                },
+            };
+
+            // The sale ended since the search, retrying can never succeed
+            if purchase_body.reason.as_deref() == Some(NOT_FOR_SALE_REASON) {
+                session.report(
+                    EventKind::SaleEnded,
+                    format_args!(
+                        "{} is no longer for sale",
+                        asset_link.truecolor(150, 150, 150)
+                    ),
+                );
+                return Ok(PurchaseStatus::SaleEnded);
+            }
+
+            // Retrying with the stale expected price could never succeed
+            if purchase_body.reason.as_deref() == Some(PRICE_CHANGED_REASON) {
+                return retry_with_current_price(session, asset, interval, ratelimit_interval)
+                    .await;
+            }
+
+            // Only reachable with --no-owned-check, which skips the ownership check
+            if purchase_body.reason.as_deref() == Some(ALREADY_OWNED_REASON) {
+                session.report(
+                    EventKind::AlreadyOwned,
+                    format_args!("{} is already owned", asset_link.truecolor(150, 150, 150)),
+                );
+                return Ok(PurchaseStatus::AlreadyOwned);
+            }
+
+            transaction_id = purchase_body.transaction_id;
+            purchase_body.errors
+        }
+        PurchaseRoute::Collectible { .. } => {
+            get_collectible_purchase_errors(purchase_response, args).await?
+        }
+    };
+
+    if let Some(errors) = errors {
+        // The token is signed by a key kept in the browser, so it can only
+        // be forwarded and never computed here
+        if errors.iter().any(is_bound_auth_token_error) {
+            println!(
+                "{} {}",
+                "A bound auth token is required to purchase".bold().red(),
+                asset_link
+            );
+            let reason = match &args.bound_auth_token {
+                Some(_) => "The --bound-auth-token was refused, capture a fresh one",
+                None => "Purchases require a token captured from the browser, pass it with --bound-auth-token",
+            };
+            return Err(Aborted(reason.to_string()).into());
+        }
+
+        for error in errors.iter() {
+            let policy = get_retry_policy(args, error.code);
+            session.set_purchase_error(
+                Some(error.code),
+                error.message.as_deref().unwrap_or("purchase failed"),
+            );
+
+            if args.verbose {
+                println!(
+                    "{}",
+                    format!("Error code {} is handled as {}", error.code, policy)
+                        .truecolor(150, 150, 150)
+                );
+            }
+
+            if policy == RetryPolicy::Ratelimit {
+                if args.defer_ratelimited {
+                    session.ratelimits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(PurchaseStatus::Ratelimited);
+                }
+
+                session.wait_for_ratelimit(ratelimit_interval).await;
+                continue;
+            }
+
+            // Moderated items fail with generic errors, retrying them never
+            // succeeds. Checked on the first of them only, to keep retries cheap
+            if policy == RetryPolicy::Retry
+                && !session.moderation_checked.swap(true, Ordering::Relaxed)
+                && matches!(get_current_details(session, asset).await, Ok(None))
+            {
+                session.report(
+                    EventKind::Moderated,
+                    format_args!(
+                        "{} is unavailable (moderated)",
+                        asset_link.truecolor(150, 150, 150)
+                    ),
+                );
+                return Ok(PurchaseStatus::Moderated);
+            }
+
+            match &error.message {
+                Some(message) => session.report(
+                    EventKind::Failed,
+                    format_args!(
+                        "{} {} ({})",
+                        "Failed to purchase".bold().red(),
+                        asset_link,
+                        message
+                    ),
+                ),
+                None => session.report(
+                    EventKind::Failed,
+                    format_args!("{} {}", "Failed to purchase".bold().red(), asset_link),
+                ),
+            }
+
+            // With --fail-fast, any error other than a ratelimit stops the
+            // run, skipped codes included
+            if policy == RetryPolicy::Abort || args.fail_fast {
+                return Err(Aborted(format!(
+                    "Failed to purchase {}: error code {}",
+                    asset.name, error.code
+                ))
+                .into());
+            }
+
+            if policy == RetryPolicy::Skip {
+                return Ok(PurchaseStatus::Failed);
+            }
+        }
+
+        session.record_retry()?;
+        return try_purchase(session, asset, interval, ratelimit_interval).await;
+    }
+
+    if args.confirm_purchases {
+        match authenticated_user_owns_bundle(session, asset).await {
+            Ok(true) => {}
+            Ok(false) => {
+                session.report(
+                    EventKind::Failed,
+                    format_args!(
+                        "{} {} but it is not owned",
+                        "Purchase reported success for".bold().yellow(),
+                        asset_link
+                    ),
+                );
+                session.set_purchase_error(
+                    None,
+                    "reported as purchased but missing from the inventory",
+                );
+                tokio::time::sleep(session.get_purchase_interval(interval)).await;
+                return Ok(PurchaseStatus::Failed);
+            }
+            // The purchase went through either way, only its check failed
+            Err(error) => {
+                println!(
+                    "{} Couldn't confirm the purchase of {} ({})",
+                    "Warning".bold().yellow(),
+                    asset_link,
+                    error
+                );
+                session.unconfirmed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    if let Some(throttle) = &session.throttle {
+        throttle.on_success();
+    }
+    session.ratelimit_streak.store(0, Ordering::Relaxed);
+
+    session.record_spend(price)?;
+
+    session.report(
+        EventKind::Purchased,
+        format_args!("{} {}", "Purchased".bold().green(), asset_link),
+    );
+
+    if let Some(transaction_id) = &transaction_id {
+        session.record_receipt(asset, price, transaction_id);
+    }
+
+    if let Some(command) = &args.on_purchase {
+        run_purchase_hook(command, asset);
+    }
+
+    tokio::time::sleep(session.get_purchase_interval(interval)).await;
+
+    Ok(PurchaseStatus::Purchased)
+}
+
+/// Checks every purchase of the run against the inventory, counting items
+/// that were reported as purchased but aren't owned as failures
+async fn reconcile_ownership(session: &Session, summary: &mut RunSummary) {
+    let mut owned: u32 = 0;
+    let mut unchecked: u32 = 0;
+
+    for item in summary.purchased_items.iter() {
+        match authenticated_user_owns_bundle(session, item).await {
+            Ok(true) => owned += 1,
+            Ok(false) => {}
+            Err(error) => {
+                println!(
+                    "{} Couldn't check the ownership of {} ({})",
+                    "Warning".bold().yellow(),
+                    item.name,
+                    error
+                );
+                unchecked += 1;
+            }
+        }
+    }
+
+    // Items that failed their check are still counted as purchased
+    summary.unconfirmed += unchecked;
+    let missing = summary.purchased.saturating_sub(owned + unchecked);
+
+    if missing > 0 {
+        println!(
+            "{} {} purchased items are not owned",
+            "Reconciled ownership:".bold().yellow(),
+            missing
+        );
+        summary.purchased -= missing;
+        summary.failed += missing;
+    }
+}
+
+fn prioritize_assets(args: &Args, assets: &mut [MarketplaceQueryResponseItem]) {
+    match args.purchase_order {
+        // Items without a price can't be purchased and are kept at the end
+        PurchaseOrder::Cheapest => assets.sort_by_key(|asset| asset.price.unwrap_or(u32::MAX)),
+        // Asset ids are assigned in upload order
+        PurchaseOrder::Newest => assets.sort_by_key(|asset| std::cmp::Reverse(asset.id)),
+        PurchaseOrder::Favorites => {
+            if assets.iter().all(|asset| asset.favorite_count.is_none()) {
+                if args.verbose {
+                    println!(
+                        "{}",
+                        "No favorite counts on this page, keeping the listed order"
+                            .truecolor(150, 150, 150)
+                    );
+                }
+                return;
+            }
+
+            // Items without a favorite count are kept at the end of the page
+            assets.sort_by_key(|asset| std::cmp::Reverse(asset.favorite_count));
+        }
+        // Items without a deadline stay on sale and are kept at the end
+        PurchaseOrder::Expiring => assets.sort_by_key(|asset| {
+            (
+                asset.off_sale_deadline.is_none(),
+                asset.off_sale_deadline.map(|deadline| deadline.0),
+            )
+        }),
+        PurchaseOrder::AsListed => {}
+    }
+}
+
+fn is_creator_type_included(args: &Args, asset: &MarketplaceQueryResponseItem) -> bool {
+    let included = args.creator_types.iter().any(|creator_type| {
+        creator_type
+            .to_possible_value()
+            .is_some_and(|value| value.matches(&asset.creator_type, true))
+    });
+
+    if !included && args.verbose {
+        println!(
+            "{}",
+            format!(
+                "{} skipped, published by a {} creator",
+                asset.name, asset.creator_type
+            )
+            .truecolor(150, 150, 150)
+        );
+    }
+
+    included
+}
+
+fn is_within_favorite_range(args: &Args, asset: &MarketplaceQueryResponseItem) -> bool {
+    if args.min_favorites.is_none() && args.max_favorites.is_none() {
+        return true;
+    }
+
+    let Some(favorite_count) = asset.favorite_count else {
+        if args.verbose {
+            println!(
+                "{}",
+                format!("{} has no favorite count, keeping it", asset.name)
+                    .truecolor(150, 150, 150)
+            );
+        }
+        return true;
+    };
+
+    let within_range = args.min_favorites.is_none_or(|min| favorite_count >= min)
+        && args.max_favorites.is_none_or(|max| favorite_count <= max);
+
+    if !within_range && args.verbose {
+        println!(
+            "{}",
+            format!(
+                "{} skipped, {} favorites is outside the configured range",
+                asset.name, favorite_count
+            )
+            .truecolor(150, 150, 150)
+        );
+    }
+
+    within_range
+}
+
+fn print_creator_breakdown(purchases_per_creator: &HashMap<u64, u32>) {
+    let mut creators: Vec<(&u64, &u32)> = purchases_per_creator.iter().collect();
+    creators.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+    for (creator_target_id, purchases) in creators {
+        println!(
+            "  Creator {}: {} items",
+            creator_target_id,
+            purchases.to_string().bold().blue()
+        );
+    }
+}
+
+fn parse_probability(value: &str) -> Result<f64, String> {
+    let probability = value
+        .parse::<f64>()
+        .map_err(|_| format!("{:?} isn't a number", value))?;
+
+    if probability <= 0.0 || probability >= 1.0 {
+        return Err("must be between 0 and 1".to_string());
+    }
+
+    Ok(probability)
+}
+
+/// A language tag like `en` or `en-us`
+fn parse_locale(value: &str) -> Result<String, String> {
+    let is_valid = !value.is_empty()
+        && value.split('-').all(|part| {
+            (1..=8).contains(&part.len())
+                && part
+                    .chars()
+                    .all(|character| character.is_ascii_alphanumeric())
+        });
+
+    if !is_valid {
+        return Err("expected a locale like en or en-us".to_string());
+    }
+
+    Ok(value.to_lowercase())
+}
+
+fn parse_args() -> Result<Args, Box<dyn std::error::Error>> {
+    let command_line: Vec<OsString> = std::env::args_os().collect();
+    let matches = Args::command().get_matches_from(&command_line);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    if let Some(config_path) = &args.config {
+        args = Config::load(config_path)?.apply(&command_line, &matches)?;
+    }
+
+    validate_category(&args)?;
+    categories::resolve_categories(&mut args)?;
+    validate_source(&args)?;
+    validate_retry_codes(&args)?;
+
+    Ok(args)
+}
+
+async fn run() -> Result<RunSummary, Box<dyn std::error::Error>> {
+    let mut args = parse_args()?;
+    let client = build_client(&args)?;
+    let timings = Arc::new(Timings::new(args.timings));
+
+    if let Some(Command::Search { dump }) = &args.command {
+        let dumped_items = dump::dump_catalog(&client, &args, &timings, dump).await?;
+
+        println!(
+            "{} Wrote {} items to {}",
+            "Done".bold().green(),
+            dumped_items.to_string().bold().blue(),
+            dump.display()
+        );
+        timings.print_summary();
+
+        return Ok(RunSummary::default());
+    }
+
+    if let Some(Command::State { action }) = &args.command {
+        match action {
+            StateAction::List => state::list_checkpoints(&args.state_dir)?,
+            StateAction::Clear { older_than_hours } => {
+                let removed = state::clear_checkpoints(
+                    &args.state_dir,
+                    older_than_hours.map(|hours| Duration::from_secs(hours * 3600)),
+                )?;
+                println!("{} Removed {} checkpoints", "Done".bold().green(), removed);
+            }
+        }
+
+        return Ok(RunSummary::default());
+    }
+
+    network::wait_for_connection(&client, &args).await?;
+
+    let retry_ids_path = match args.command.take() {
+        Some(Command::Retry { failures }) => Some(prepare_retry(&mut args, &failures)?),
+        command => {
+            args.command = command;
+            None
+        }
+    };
+
+    let accounts = match &args.auth_file {
+        Some(path) => account::load_auth_file(path)?,
+        None => Vec::new(),
+    };
+
+    if args.auth.is_none() && (accounts.is_empty() || args.command.is_some()) {
+        return Err("--auth is required to purchase items".into());
+    }
+
+    if let Some(Command::Whoami) = &args.command {
+        account::print_whoami(&client, &args).await?;
+        return Ok(RunSummary::default());
+    }
+
+    if let Some(Command::Doctor) = &args.command {
+        doctor::run_checks(&client, &args, &timings).await?;
+        return Ok(RunSummary::default());
+    }
+
+    if args.benchmark {
+        benchmark::run_benchmark(&client, &args, &timings).await?;
+        timings.print_summary();
+        return Ok(RunSummary::default());
+    }
+
+    if let Some(Command::Audit { unowned }) = &args.command {
+        let unowned = unowned.clone();
+        let user_id = get_authenticated_user(&client, &args).await?.id;
+        // Nothing is purchased, so no CSRF token is needed
+        let session = Session::new(client, args, timings, user_id, String::new());
+        let audit = audit::audit_catalog(&session, unowned.as_deref()).await?;
+
+        println!(
+            "{} Owned {} of {} items",
+            "Done".bold().green(),
+            audit.owned.to_string().bold().blue(),
+            (audit.owned + audit.unowned).to_string().bold().blue()
+        );
+        if let Some(path) = &unowned {
+            println!(
+                "  Wrote {} unowned item ids to {}",
+                audit.unowned,
+                path.display()
+            );
+        }
+        session.timings.print_summary();
+
+        return Ok(RunSummary::default());
+    }
+
+    if let Some(Command::ExportOwned { path }) = &args.command {
+        let path = path.clone();
+        let user_id = get_authenticated_user(&client, &args).await?.id;
+        let session = Session::new(client, args, timings, user_id, String::new());
+        let exported_items = export::export_owned(&session, &path).await?;
+
+        println!(
+            "{} Wrote {} owned items to {}",
+            "Done".bold().green(),
+            exported_items.to_string().bold().blue(),
+            path.display()
+        );
+        session.timings.print_summary();
+
+        return Ok(RunSummary::default());
+    }
+
+    let manifest = args.manifest.clone().map(|path| (path, args.clone()));
+    // --rate is at least 1, from the command line and configs alike
+    let rate_limiter = args
+        .rate
+        .and_then(NonZeroU32::new)
+        .map(|rate| Arc::new(RateLimiter::new(rate)));
+    let summary = if accounts.is_empty() {
+        purchase_catalog(client, args, timings, rate_limiter.clone()).await
+    } else {
+        run_accounts(client, args, timings, rate_limiter.clone(), accounts).await
+    };
+
+    if let Some(path) = retry_ids_path {
+        let _ = fs::remove_file(path);
+    }
+    let summary = summary?;
+
+    if let Some(rate_limiter) = rate_limiter {
+        rate_limiter.print_summary();
+    }
+
+    if let Some((path, args)) = manifest {
+        manifest::write_manifest(&path, &args, &summary)?;
+    }
+
+    Ok(summary)
+}
+
+/// Points the run at the ids of a failures file worth trying again, with
+/// the failures of this run going to a fresh file next to it. Returns the
+/// ids file written for the run
+fn prepare_retry(
+    args: &mut Args,
+    failures_path: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (ids, permanent) = failures::load_retryable_ids(failures_path)?;
+
+    println!(
+        "{}",
+        format!(
+            "Retrying {} failed items, leaving out {} that can't succeed",
+            ids.len(),
+            permanent
+        )
+        .truecolor(150, 150, 150)
+    );
+
+    let retry_failures_path = args
+        .failures
+        .clone()
+        .unwrap_or_else(|| failures_path.with_extension("retry.tsv"));
+
+    if retry_failures_path == failures_path {
+        return Err("--failures must be another file than the one being retried".into());
+    }
+
+    // Fresh, so it only holds what failed again
+    File::create(&retry_failures_path).map_err(|error| {
+        format!(
+            "Failed to create failures file {}: {}",
+            retry_failures_path.display(),
+            error
+        )
+    })?;
+
+    let ids_path = std::env::temp_dir().join(format!("fugc-retry-{}.txt", std::process::id()));
+    fs::write(
+        &ids_path,
+        ids.iter().map(|id| format!("{}\n", id)).collect::<String>(),
+    )?;
+
+    args.ids_file = Some(ids_path.clone());
+    args.favorites = None;
+    args.creator_store = None;
+    args.failures = Some(retry_failures_path);
+
+    Ok(ids_path)
+}
+
+/// Runs the whole purchase run once per account of --auth-file, one account
+/// after the other
+async fn run_accounts(
+    client: Client,
+    args: Args,
+    timings: Arc<Timings>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    mut accounts: Vec<String>,
+) -> Result<RunSummary, Box<dyn std::error::Error>> {
+    if args.randomize_accounts {
+        account::shuffle(&mut accounts);
+    }
+
+    let account_count = accounts.len();
+    let mut total = RunSummary::default();
+
+    for (index, cookie) in accounts.into_iter().enumerate() {
+        println!(
+            "{}",
+            format!("Account {} of {}", index + 1, account_count).bold()
+        );
+
+        let mut account_args = args.clone();
+        account_args.auth = Some(cookie);
+        // A CSRF token only works for the account it was issued to
+        account_args.csrf_token = None;
+
+        let summary = purchase_catalog(
+            client.clone(),
+            account_args,
+            timings.clone(),
+            rate_limiter.clone(),
+        )
+        .await?;
+        total.add(&summary);
+    }
+
+    println!(
+        "{} Bought {} items across {} accounts",
+        "Done".bold().green(),
+        total.purchased.to_string().bold().blue(),
+        account_count
+    );
+
+    Ok(total)
+}
+
+/// Attempts a purchase, turning errors into a failed purchase with
+/// --skip-failed-pages
+async fn purchase_item(
+    session: &Session,
+    asset: &MarketplaceQueryResponseItem,
+    interval: Duration,
+    ratelimit_interval: Duration,
+) -> Result<PurchaseOutcome, Box<dyn std::error::Error>> {
+    match attempt_purchase(session, asset, interval, ratelimit_interval).await {
+        Ok(outcome) => Ok(outcome),
+        Err(error) if session.args.skip_failed_pages && !error.is::<Aborted>() => {
+            session.report(
+                EventKind::Failed,
+                format_args!(
+                    "{} {} ({})",
+                    "Failed to purchase".bold().red(),
+                    asset.name,
+                    error
+                ),
+            );
+            Ok(PurchaseOutcome {
+                item: ItemSummary::from(asset),
+                status: PurchaseStatus::Failed,
+                attempts: 1,
+                spent: 0,
+                error: Some(ApiErrorInfo {
+                    code: None,
+                    message: error.to_string(),
+                }),
+            })
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Counts the outcome of a purchase in the summary and the logs
+fn record_purchase(
+    session: &Session,
+    summary: &mut RunSummary,
+    failures: &Option<FailuresFile>,
+    asset: &MarketplaceQueryResponseItem,
+    outcome: &PurchaseOutcome,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (id, name) = (outcome.item.id, outcome.item.name.as_str());
+    let status = &outcome.status;
+
+    match status {
+        PurchaseStatus::Purchased => {
+            session.log_event(&PurchaseEvent::Purchased {
+                id,
+                name,
+                price: outcome.spent,
+            });
+            summary.purchased += 1;
+            *summary
+                .purchases_per_creator
+                .entry(asset.creator_target_id)
+                .or_insert(0) += 1;
+
+            if session.args.reconcile_ownership {
+                summary.purchased_items.push(asset.clone());
+            }
+        }
+        PurchaseStatus::Skipped => {
+            session.log_event(&PurchaseEvent::Skipped { id, name });
+            summary.skipped += 1;
+        }
+        PurchaseStatus::SaleEnded => {
+            session.log_event(&PurchaseEvent::SaleEnded { id, name });
+            summary.sale_ended += 1;
+        }
+        PurchaseStatus::AlreadyOwned => {
+            session.log_event(&PurchaseEvent::AlreadyOwned { id, name });
+            summary.already_owned += 1;
+        }
+        PurchaseStatus::NotFound => {
+            session.log_event(&PurchaseEvent::NotFound { id, name });
+            summary.not_found += 1;
+        }
+        PurchaseStatus::Moderated => {
+            session.log_event(&PurchaseEvent::Moderated { id, name });
+            summary.skipped += 1;
+            summary.moderated += 1;
+        }
+        // Deferred by the caller, nothing happened to count yet
+        PurchaseStatus::Ratelimited => {}
+        PurchaseStatus::Failed => {
+            session.log_event(&PurchaseEvent::Failed { id, name });
+            summary.failed += 1;
+
+            if let Some(failures) = failures {
+                failures.record(id, &outcome.get_failure_reason())?;
+            }
+        }
+    }
+
+    if let Some(live_table) = session
+        .live_table
+        .as_ref()
+        .filter(|_| session.shows(status.event_kind()))
+    {
+        live_table.lock().unwrap().add(
+            asset,
+            status,
+            summary,
+            session.spent.load(Ordering::Relaxed),
+        );
+    }
+
+    Ok(())
+}
+
+/// Tries the deferred purchases whose cooldown passed again. With
+/// `wait_for_all`, waits out the remaining cooldowns until none are left
+async fn retry_deferred(
+    session: &Session,
+    summary: &mut RunSummary,
+    failures: &Option<FailuresFile>,
+    deferred_retries: &mut DeferredRetries,
+    interval: Duration,
+    ratelimit_interval: Duration,
+    wait_for_all: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        let asset = match deferred_retries.pop_ready(Instant::now()) {
+            Some(asset) => asset,
+            None => match deferred_retries.next_ready_at() {
+                Some(ready_at) if wait_for_all => {
+                    println!(
+                        "{}",
+                        format!(
+                            "Waiting for the cooldown of {} ratelimited purchases",
+                            deferred_retries.len()
+                        )
+                        .truecolor(150, 150, 150)
+                    );
+                    tokio::time::sleep_until(ready_at.into()).await;
+                    continue;
+                }
+                _ => return Ok(()),
+            },
+        };
+
+        let outcome = purchase_item(session, &asset, interval, ratelimit_interval).await?;
+
+        if matches!(outcome.status, PurchaseStatus::Ratelimited) {
+            deferred_retries.push(asset, Instant::now() + ratelimit_interval);
+            continue;
+        }
+
+        record_purchase(session, summary, failures, &asset, &outcome)?;
+    }
+}
+
+async fn purchase_catalog(
+    client: Client,
+    args: Args,
+    timings: Arc<Timings>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+) -> Result<RunSummary, Box<dyn std::error::Error>> {
+    let csrf_token = match &args.csrf_token {
+        Some(csrf_token) => csrf_token.clone(),
+        None => get_csrf_token(&client, &args).await?,
+    };
+    let user_id = get_authenticated_user(&client, &args).await?.id;
+    account::check_account_state(&client, &args).await?;
+
+    if let Some(group_id) = args.use_group_funds {
+        account::validate_group_funds(&client, &args, group_id).await?;
+    } else if args.max_spend.is_some() {
+        let robux = account::get_robux_balance(&client, &args, user_id).await?;
+        account::warn_if_balance_is_short(&args, robux);
+    }
+
+    let mut session = Session::new(client, args, timings, user_id, csrf_token);
+    session.rate_limiter = rate_limiter;
+    if let Some(path) = &session.args.event_log {
+        if session.args.skip_reported {
+            let (purchased_ids, other_accounts) = events::load_purchased_ids(path, user_id)?;
+
+            if other_accounts > 0 {
+                println!(
+                    "{} Ignoring {} purchases the event log records for other accounts",
+                    "Warning".bold().yellow(),
+                    other_accounts
+                );
+            }
+            println!(
+                "{}",
+                format!(
+                    "Skipping {} items purchased in earlier runs",
+                    purchased_ids.len()
+                )
+                .truecolor(150, 150, 150)
+            );
+            session.reported_ids = purchased_ids;
+        }
+
+        session.event_log = Some(EventLog::create(path)?);
+        session.log_event(&PurchaseEvent::Started { user_id });
+    }
+    if let Some(path) = &session.args.receipts {
+        session.receipts = Some(ReceiptsFile::create(path)?);
+    }
+    if session.args.format == Format::Table {
+        session.live_table = Some(Mutex::new(LiveTable::new()));
+    }
+    let failures = match &session.args.failures {
+        Some(path) => Some(FailuresFile::create(path)?),
+        None => None,
+    };
+    let client = &session.client;
+    let args = &session.args;
+    let timings = &session.timings;
+
+    let interval = Duration::from_millis(args.purchase_interval_ms);
+    let ratelimit_interval = Duration::from_millis(args.ratelimit_interval_ms);
+
+    let mut summary = RunSummary {
+        user_ids: vec![session.user_id],
+        ..Default::default()
+    };
+    let mut deferred_retries = DeferredRetries::default();
+    let mut creator_cooldowns = args
+        .creator_cooldown_ms
+        .map(|cooldown| CreatorCooldowns::new(Duration::from_millis(cooldown)));
+    // Besides --watch, the search split by creator type repeats items of the
+    // full search, which only the ownership check would skip otherwise
+    let remember_seen =
+        args.watch || (args.no_owned_check && get_next_search_slice(args, None).is_some());
+    let mut seen_items = if args.seen_bloom {
+        SeenItems::Bloom(BloomFilter::new(
+            args.seen_bloom_capacity,
+            args.seen_bloom_fp_rate,
+        ))
+    } else {
+        SeenItems::Exact(HashSet::new())
+    };
+    let sampler = args.sample_rate.map(|sample_rate| {
+        let seed = args.seed.unwrap_or_else(Sampler::random_seed);
+        println!(
+            "{}",
+            format!("Sampling items with --seed {}", seed).truecolor(150, 150, 150)
+        );
+        Sampler::new(sample_rate, seed)
+    });
+    let mut progress = Progress::new();
+    if let Some(start_at) = args.start_at {
+        schedule::wait_until(client, args, timings, start_at).await?;
+    }
+
+    pause::listen_for_pause_signal();
+
+    let mut checkpoint = if args.resume {
+        state::load_checkpoint(args)?
+    } else {
+        None
+    };
+
+    loop {
+        let mut next_page_cursor: Option<String> = None;
+        let mut page_index: u32 = 0;
+
+        if let Some(checkpoint) = checkpoint.take() {
+            println!(
+                "{}",
+                format!("Resuming from page {}", checkpoint.page_index + 1)
+                    .truecolor(150, 150, 150)
+            );
+            next_page_cursor = Some(checkpoint.next_page_cursor);
+            page_index = checkpoint.page_index;
+        }
+
+        // Favorites and gamepasses are read a page at a time, prefetching
+        // only covers the search
+        let mut prefetched_pages = if get_prefetch_buffer_size(args) > 0
+            && !args.reverse
+            && args.source == Source::Catalog
+            && args.favorites.is_none()
+            && args.ids_file.is_none()
+            && args.creator_store.is_none()
+        {
+            Some(spawn_page_prefetcher(
+                client.clone(),
+                args.clone(),
+                timings.clone(),
+                next_page_cursor.clone(),
+            ))
+        } else {
+            None
+        };
+
+        let mut reversed_pages = if args.reverse {
+            Some(ReversedPages::collect(&session, next_page_cursor.clone()).await?)
+        } else {
+            None
+        };
+        // The search narrowed to one creator type once it stopped early
+        let mut slice_args: Option<Args> = None;
+
+        loop {
+            if output::is_stdout_closed() {
+                break;
+            }
+
+            let response = match &mut prefetched_pages {
+                Some(receiver) => match receiver.recv().await {
+                    Some(response) => {
+                        response.map_err(|error| error as Box<dyn std::error::Error>)?
+                    }
+                    None => break,
+                },
+                None => match &mut reversed_pages {
+                    Some(reversed_pages) => match reversed_pages.next(&session).await? {
+                        Some(response) => response,
+                        None => break,
+                    },
+                    None => match &slice_args {
+                        Some(slice_args) => {
+                            get_search_page(client, slice_args, timings, &next_page_cursor)
+                                .await
+                                .map_err(|error| error as Box<dyn std::error::Error>)?
+                        }
+                        None => get_page(&session, &next_page_cursor).await?,
+                    },
+                },
+            };
+
+            progress.set_total_items(response.total_results);
+            let page_len = response.data.as_ref().map_or(0, Vec::len);
+            let requested_page_size = response.page_size;
+
+            let mut assets = response.data.unwrap_or_default();
+
+            if args
+                .shard
+                .is_some_and(|shard| !shard.includes_page(page_index))
+            {
+                assets.clear();
+            }
+
+            page_index += 1;
+
+            prioritize_assets(args, &mut assets);
+
+            if let Some(sampler) = &sampler {
+                let page_size = assets.len();
+                assets.retain(|asset| {
+                    let keep = sampler.keep(asset.id);
+                    // Left out for good, later polls of --watch decide the same
+                    if !keep && args.watch {
+                        seen_items.insert(asset.id);
+                    }
+                    keep
+                });
+                summary.skipped += (page_size - assets.len()) as u32;
+            }
+
+            check_page_ownership(&session, &assets).await;
+
+            let mut owned_on_page: usize = 0;
+            let (mut attempted_on_page, mut failed_on_page): (usize, usize) = (0, 0);
+            let mut not_found_streak: u32 = 0;
+
+            let mut candidates = Vec::with_capacity(assets.len());
+
+            for asset in assets.iter() {
+                if remember_seen && !seen_items.insert(asset.id) {
+                    continue;
+                }
+
+                if !is_within_favorite_range(args, asset) || !is_creator_type_included(args, asset)
+                {
+                    summary.skipped += 1;
+                    continue;
+                }
+
+                candidates.push(asset);
+            }
+
+            // Availability only waits on requests, so the whole page is checked
+            // up front while the purchases stay one at a time
+            let availabilities = join::join_bounded(
+                candidates
+                    .iter()
+                    .map(|asset| is_asset_available(&session, asset)),
+                args.ownership_concurrency as usize,
+            )
+            .await;
+
+            let mut queue: VecDeque<_> = candidates.into_iter().zip(availabilities).collect();
+
+            while !queue.is_empty() {
+                // Only items about to be purchased wait on their creator's cooldown
+                let (index, ready_at) = match &creator_cooldowns {
+                    Some(creator_cooldowns) => creator_cooldowns.pick(
+                        &queue,
+                        |(asset, availability)| {
+                            matches!(availability, Ok(Availability::Available))
+                                .then_some(asset.creator_target_id)
+                        },
+                        Instant::now(),
+                    ),
+                    None => (0, None),
+                };
+
+                if let Some(ready_at) = ready_at {
+                    tokio::time::sleep_until(ready_at.into()).await;
+                }
+
+                if index > 0 {
+                    summary.creator_reorders += 1;
+                }
+
+                let Some((asset, availability)) = queue.remove(index) else {
+                    break;
+                };
+
+                let creator_purchases = summary
+                    .purchases_per_creator
+                    .get(&asset.creator_target_id)
+                    .copied()
+                    .unwrap_or(0);
+
+                if args
+                    .max_per_creator
+                    .is_some_and(|max_per_creator| creator_purchases >= max_per_creator)
+                {
+                    session.report(
+                        EventKind::Skipped,
+                        format_args!(
+                            "{} skipped, reached the purchase cap for creator {}",
+                            asset.name.truecolor(150, 150, 150),
+                            asset.creator_target_id
+                        ),
+                    );
+                    summary.skipped += 1;
+                    continue;
+                }
+
+                pause::wait_while_paused().await;
+
+                if output::is_stdout_closed() {
+                    break;
+                }
+
+                retry_deferred(
+                    &session,
+                    &mut summary,
+                    &failures,
+                    &mut deferred_retries,
+                    interval,
+                    ratelimit_interval,
+                    false,
+                )
+                .await?;
+
+                if let Some(deadline) = asset
+                    .off_sale_deadline
+                    .filter(|deadline| deadline.0 <= SystemTime::now())
+                {
+                    session.report(
+                        EventKind::SaleEnded,
+                        format_args!(
+                            "{} sale ended at {}",
+                            asset.name.truecolor(150, 150, 150),
+                            deadline
+                        ),
+                    );
+                    session.log_event(&PurchaseEvent::SaleEnded {
+                        id: asset.id,
+                        name: &asset.name,
+                    });
+                    summary.sale_ended += 1;
+                    continue;
+                }
+
+                let availability = availability?;
+
+                if availability == Availability::Owned {
+                    owned_on_page += 1;
+                }
+
+                if availability == Availability::Unknown {
+                    summary.skipped += 1;
+                }
+
+                if availability == Availability::OwnCreation {
+                    session.report(
+                        EventKind::Skipped,
+                        format_args!(
+                            "{} skipped, it is your own creation",
+                            asset.name.truecolor(150, 150, 150)
+                        ),
+                    );
+                    summary.skipped += 1;
+                }
+
+                if availability == Availability::Available {
+                    let outcome =
+                        purchase_item(&session, asset, interval, ratelimit_interval).await?;
+
+                    if let Some(creator_cooldowns) = &mut creator_cooldowns {
+                        creator_cooldowns.record_purchase(asset.creator_target_id, Instant::now());
+                    }
+
+                    if matches!(outcome.status, PurchaseStatus::Ratelimited) {
+                        deferred_retries.push(asset.clone(), Instant::now() + ratelimit_interval);
+                        summary.deferred += 1;
+                        continue;
+                    }
+
+                    attempted_on_page += 1;
+
+                    if matches!(outcome.status, PurchaseStatus::NotFound) {
+                        not_found_streak += 1;
+                    } else {
+                        not_found_streak = 0;
+                    }
+
+                    match outcome.status {
+                        PurchaseStatus::AlreadyOwned => owned_on_page += 1,
+                        PurchaseStatus::Failed => failed_on_page += 1,
+                        _ => {}
+                    }
+
+                    record_purchase(&session, &mut summary, &failures, asset, &outcome)?;
+
+                    if args
+                        .max_404_streak
+                        .is_some_and(|max_404_streak| not_found_streak >= max_404_streak)
+                    {
+                        println!(
+                            "{} {} items in a row no longer exist, skipping the rest of page {}",
+                            "Warning".bold().yellow(),
+                            not_found_streak,
+                            page_index
+                        );
+                        summary.not_found_streaks += 1;
+                        break;
+                    }
+                }
+            }
+
+            progress.add_processed_items(assets.len());
+            if !args.quiet {
+                progress.print(page_index);
+            }
+
+            if attempted_on_page > 0 && failed_on_page == attempted_on_page {
+                summary.failed_pages += 1;
+
+                if args.skip_failed_pages {
+                    println!(
+                        "{} Every purchase on page {} failed, moving on to the next page",
+                        "Warning".bold().yellow(),
+                        page_index
+                    );
+                }
+            }
+
+            if args.skip_completed_subcategories
+                && !assets.is_empty()
+                && owned_on_page == assets.len()
+            {
+                println!(
+                    "{}",
+                    "Every item on this page is already owned, skipping the rest of the subcategory"
+                        .truecolor(150, 150, 150)
+                );
+                break;
+            }
+
+            // Cursors of a narrowed search mean nothing to the full one
+            if args.resume && slice_args.is_none() {
+                if let Some(cursor) = &response.next_page_cursor {
+                    state::save_checkpoint(
+                        args,
+                        &Checkpoint::new(args, cursor.clone(), page_index),
+                    )?;
+                }
+            }
+
+            // Reversed pages end when the collected ones ran out instead
+            if response.next_page_cursor.is_none() && reversed_pages.is_none() {
+                let current_slice = slice_args.as_ref().and_then(|args| args.search_slice);
+                let stopped_early = page_len >= requested_page_size;
+
+                // Once narrowed, every creator type is searched in turn
+                let next_slice = if stopped_early || current_slice.is_some() {
+                    get_next_search_slice(args, current_slice)
+                } else {
+                    None
+                };
+
+                if stopped_early {
+                    let search = match current_slice {
+                        Some(creator_type) => {
+                            format!("search of {} creators", creator_type.get_api_name())
+                        }
+                        None => "search".to_string(),
+                    };
+                    let reason = if next_slice.is_some() && current_slice.is_none() {
+                        "searching each creator type on its own"
+                    } else {
+                        "the items past it can't be reached"
+                    };
+                    println!(
+                        "{} The {} stopped at page {} with full pages left, {}",
+                        "Warning".bold().yellow(),
+                        search,
+                        page_index,
+                        reason
+                    );
+                }
+
+                let Some(next_slice) = next_slice else {
+                    break;
+                };
+
+                println!(
+                    "{}",
+                    format!(
+                        "Searching the items of {} creators",
+                        next_slice.get_api_name()
+                    )
+                    .truecolor(150, 150, 150)
+                );
+                prefetched_pages = None;
+                slice_args = Some(Args {
+                    search_slice: Some(next_slice),
+                    ..args.clone()
+                });
+                next_page_cursor = None;
+                continue;
+            }
+
+            next_page_cursor = response.next_page_cursor;
+        }
+
+        // The output was closed, the run stops short of the end
+        if output::is_stdout_closed() {
+            break;
+        }
+
+        retry_deferred(
+            &session,
+            &mut summary,
+            &failures,
+            &mut deferred_retries,
+            interval,
+            ratelimit_interval,
+            true,
+        )
+        .await?;
+
+        // The crawl is complete, the next run starts over
+        if args.resume {
+            state::remove_checkpoint(args)?;
+        }
+
+        if !args.watch {
+            break;
+        }
+
+        println!(
+            "Reached the end of the catalog, searching again in {} seconds",
+            args.poll_interval
+        );
+        tokio::time::sleep(Duration::from_secs(args.poll_interval)).await;
+    }
+
+    summary.retries = session.retries.load(Ordering::Relaxed);
+    summary.ignored_ids = session.ignored_ids.load(Ordering::Relaxed);
+    summary.unconfirmed = session.unconfirmed.load(Ordering::Relaxed);
+    summary.spent = session.spent.load(Ordering::Relaxed);
+    summary.ratelimits = session.ratelimits.load(Ordering::Relaxed);
+    summary.ratelimit_wait = *session.ratelimit_wait.lock().unwrap();
+
+    if args.reconcile_ownership {
+        reconcile_ownership(&session, &mut summary).await;
+    }
+
+    println!(
+        "{} Bought {} items",
+        "Done".bold().green(),
+        summary.purchased.to_string().bold().blue()
+    );
+
+    if summary.sale_ended > 0 {
+        println!(
+            "  Skipped {} items whose sale ended",
+            summary.sale_ended.to_string().bold().blue()
+        );
+    }
+
+    if summary.spent > 0 {
+        println!("  Spent {} Robux", summary.spent.to_string().bold().blue());
+    }
+
+    if summary.already_owned > 0 {
+        println!(
+            "  Skipped {} items that were already owned",
+            summary.already_owned.to_string().bold().blue()
+        );
+    }
+
+    if summary.not_found > 0 {
+        println!(
+            "  Skipped {} items that no longer exist",
+            summary.not_found.to_string().bold().blue()
+        );
+    }
+
+    if summary.moderated > 0 {
+        println!(
+            "  Skipped {} items that are unavailable (moderated)",
+            summary.moderated.to_string().bold().blue()
+        );
+    }
+
+    if summary.ignored_ids > 0 {
+        println!(
+            "{} Ignored {} malformed lines of the ids file",
+            "Warning".bold().yellow(),
+            summary.ignored_ids.to_string().bold().red()
+        );
+    }
+
+    if summary.unconfirmed > 0 {
+        println!(
+            "{} Couldn't confirm {} purchases, check your inventory for them",
+            "Warning".bold().yellow(),
+            summary.unconfirmed.to_string().bold().red()
+        );
+    }
+
+    if summary.deferred > 0 {
+        println!(
+            "  Set {} ratelimited purchases aside to try again later",
+            summary.deferred.to_string().bold().blue()
+        );
+    }
+
+    if summary.not_found_streaks > 0 {
+        println!(
+            "  Left {} pages early after {} missing items in a row",
+            summary.not_found_streaks.to_string().bold().blue(),
+            args.max_404_streak.unwrap_or_default()
+        );
+    }
+
+    if summary.creator_reorders > 0 {
+        println!(
+            "  Reordered {} items to respect --creator-cooldown-ms",
+            summary.creator_reorders.to_string().bold().blue()
+        );
+    }
+
+    if summary.retries > 0 {
+        println!(
+            "  Retried purchases {} times",
+            summary.retries.to_string().bold().blue()
+        );
+    }
+
+    if summary.ratelimits > 0 {
+        println!(
+            "  Rate limited {} times, waited {} total",
+            summary.ratelimits.to_string().bold().blue(),
+            format_duration(summary.ratelimit_wait).bold().blue()
+        );
+    }
+
+    if summary.failed > 0 {
+        println!(
+            "{} Failed to purchase {} items",
+            "Warning".bold().yellow(),
+            summary.failed.to_string().bold().red()
+        );
+    }
+
+    if summary.failed_pages > 0 {
+        println!(
+            "  Every purchase failed on {} pages",
+            summary.failed_pages.to_string().bold().red()
+        );
+    }
+
+    if args.verbose {
+        println!(
+            "  Skipped {} items",
+            summary.skipped.to_string().bold().blue()
+        );
+        print_creator_breakdown(&summary.purchases_per_creator);
+    }
+
+    timings.print_summary();
+
+    session.log_event(&PurchaseEvent::Finished {
+        purchased: summary.purchased,
+        skipped: summary.skipped,
+        sale_ended: summary.sale_ended,
+        failed: summary.failed,
+        spent: summary.spent,
+    });
+    if let Some(event_log) = &session.event_log {
+        event_log.flush()?;
+    }
+
+    Ok(summary)
+}
+
+#[tokio::main]
+async fn main() {
+    let exit_code = match run().await {
+        Ok(summary) if summary.failed > 0 => EXIT_PARTIAL_FAILURE,
+        Ok(_) => EXIT_SUCCESS,
+        Err(error) => {
+            // A raw connection error says little about what to do about it
+            let error: Box<dyn std::error::Error> =
+                if network::is_connection_error(&*error) && !error.is::<Unreachable>() {
+                    Unreachable(error.to_string()).into()
+                } else {
+                    error
+                };
+            eprintln!("{} {}", "Error:".bold().red(), error);
+
+            if error.is::<Aborted>() {
+                EXIT_ABORTED
+            } else if error.is::<Unreachable>() {
+                EXIT_UNREACHABLE
+            } else {
+                EXIT_ERROR
+            }
+        }
+    };
+
+    std::process::exit(exit_code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{Cassette, Recorder};
+    use mock_server::{MockResponse, MockServer};
+
+    const BASE_URL_FLAGS: &[&str] = &[
+        "--catalog-base-url",
+        "--users-base-url",
+        "--inventory-base-url",
+        "--economy-base-url",
+        "--apis-base-url",
+        "--www-base-url",
+        "--premium-features-base-url",
+        "--groups-base-url",
+        "--games-base-url",
+        "--usermoderation-base-url",
+    ];
+
+    fn test_args(base_url: &str, extra_args: &[&str]) -> Args {
+        let mut argv = vec!["free-ugc-bulk-purchaser", "--auth", "cookie"];
+        for flag in BASE_URL_FLAGS {
+            argv.push(flag);
+            argv.push(base_url);
+        }
+        argv.extend_from_slice(extra_args);
+
+        Args::parse_from(argv)
+    }
+
+    fn test_session(server: &MockServer, extra_args: &[&str]) -> Session {
+        Session::new(
+            Client::new(),
+            test_args(&server.base_url, extra_args),
+            Arc::new(Timings::new(false)),
+            1234,
+            "csrf-token".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn throttle_never_goes_below_the_purchase_interval() {
+        let server = MockServer::start(Vec::new()).await;
+        let session = test_session(&server, &["--throttle-on-403", "--throttle-min-ms", "500"]);
+        let throttle = session.throttle.as_ref().unwrap();
+        let interval = Duration::from_millis(2000);
+
+        assert_eq!(session.get_purchase_interval(interval), interval);
+
+        throttle.on_throttled();
+        throttle.on_throttled();
+        throttle.on_throttled();
+        assert_eq!(
+            session.get_purchase_interval(interval),
+            Duration::from_millis(4000)
+        );
+
+        for _ in 0..100 {
+            throttle.on_success();
+        }
+        assert_eq!(session.get_purchase_interval(interval), interval);
+    }
+
+    fn test_asset() -> MarketplaceQueryResponseItem {
+        serde_json::from_str(
+            r#"{
+                "id": 14476435962,
+                "itemType": "Asset",
+                "name": "Free Hat",
+                "productId": 1598473352,
+                "creatorType": "User",
+                "creatorTargetId": 4372130,
+                "price": 0
+            }"#,
+        )
+        .unwrap()
+    }
+
+    // The clock is paused, so the ratelimit is waited out without sleeping
+    #[tokio::test(start_paused = true)]
+    async fn ratelimited_purchase_is_retried_once_then_purchased() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/1598473352",
+            vec![
+                MockResponse::json(
+                    200,
+                    r#"{"errors":[{"code":27,"message":"TooManyRequests"}]}"#,
+                ),
+                MockResponse::json(200, r#"{"purchased":true}"#),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+        let started_at = tokio::time::Instant::now();
+
+        let status = attempt_purchase(
+            &session,
+            &test_asset(),
+            Duration::ZERO,
+            Duration::from_secs(65),
+        )
+        .await
+        .unwrap()
+        .status;
+
+        assert!(matches!(status, PurchaseStatus::Purchased));
+        assert_eq!(session.retries.load(Ordering::Relaxed), 1);
+        assert_eq!(session.ratelimits.load(Ordering::Relaxed), 1);
+        assert!(started_at.elapsed() >= Duration::from_secs(65));
+        assert_eq!(
+            *session.ratelimit_wait.lock().unwrap(),
+            Duration::from_secs(65)
+        );
+        assert_eq!(
+            server.count_requests("POST", "/v1/purchases/products/1598473352"),
+            2
+        );
+
+        let purchase = &server.requests()[0];
+        assert_eq!(purchase.headers["x-csrf-token"], "csrf-token");
+        assert_eq!(
+            purchase.body,
+            r#"{"expectedCurrency":1,"expectedPrice":0,"expectedSellerId":4372130}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn refused_purchase_without_a_body_is_retried_as_a_ratelimit() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/1598473352",
+            vec![
+                MockResponse::json(429, "Too Many Requests"),
+                MockResponse::json(403, "<html>Access denied</html>"),
+                MockResponse::json(200, r#"{"purchased":true}"#),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Purchased));
+        assert_eq!(session.ratelimits.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn csrf_refusal_slows_down_the_throttle() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![
+                MockResponse::json(
+                    403,
+                    r#"{"errors":[{"code":0,"message":"Token Validation Failed"}]}"#,
+                )
+                .with_header("x-csrf-token", "fresh-token"),
+                MockResponse::json(200, r#"{"purchased":true}"#),
+            ],
+        )])
+        .await;
+        let session = test_session(
+            &server,
+            &[
+                "--throttle-on-403",
+                "--throttle-min-ms",
+                "1",
+                "--throttle-max-ms",
+                "10",
+            ],
+        );
+
+        attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            session.throttle.as_ref().unwrap().delay(),
+            Duration::from_millis(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_purchase_confirmation_leaves_the_item_purchased() {
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![MockResponse::json(200, r#"{"purchased":true}"#)],
+            ),
+            (
+                "GET",
+                "/v1/users/1234/items/",
+                vec![MockResponse::json(500, "Internal Server Error")],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &["--confirm-purchases"]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Purchased));
+        assert_eq!(session.unconfirmed.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn reconciliation_keeps_going_past_failed_checks() {
+        let server = MockServer::start(vec![
+            (
+                "GET",
+                "/v1/users/1234/items/Asset/1/is-owned",
+                vec![MockResponse::json(200, "true")],
+            ),
+            (
+                "GET",
+                "/v1/users/1234/items/Asset/2/is-owned",
+                vec![MockResponse::json(500, "Internal Server Error")],
+            ),
+            (
+                "GET",
+                "/v1/users/1234/items/Asset/3/is-owned",
+                vec![MockResponse::json(200, "false")],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &["--no-owned-check", "--reconcile-ownership"]);
+        let mut summary = RunSummary {
+            purchased: 2,
+            purchased_items: [1, 2, 3]
+                .map(|id| MarketplaceQueryResponseItem { id, ..test_asset() })
+                .to_vec(),
+            ..Default::default()
+        };
+
+        reconcile_ownership(&session, &mut summary).await;
+
+        assert_eq!(summary.purchased, 2);
+        assert_eq!(summary.failed, 0);
+        assert_eq!(summary.unconfirmed, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ratelimit_countdown_sleeps_a_second_at_a_time() {
+        let started_at = tokio::time::Instant::now();
+        let mut out = Vec::new();
+
+        count_down(&mut out, Duration::from_millis(2500)).await;
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(started_at.elapsed(), Duration::from_millis(2500));
+        assert_eq!(out.matches("Resuming in").count(), 3);
+        assert!(out.contains("Resuming in 3s"));
+        assert!(out.contains("Resuming in 1s"));
+    }
+
+    #[tokio::test]
+    async fn ratelimited_purchase_is_deferred_with_defer_ratelimited() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/1598473352",
+            vec![MockResponse::json(
+                200,
+                r#"{"errors":[{"code":27,"message":"TooManyRequests"}]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &["--defer-ratelimited"]);
+
+        let status = attempt_purchase(
+            &session,
+            &test_asset(),
+            Duration::ZERO,
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap()
+        .status;
+
+        assert!(matches!(status, PurchaseStatus::Ratelimited));
+        assert_eq!(session.ratelimits.load(Ordering::Relaxed), 1);
+        assert_eq!(session.retries.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn expired_csrf_token_is_replaced_and_purchase_retried() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![
+                MockResponse::json(
+                    403,
+                    r#"{"errors":[{"code":0,"message":"Token Validation Failed"}]}"#,
+                )
+                .with_header("x-csrf-token", "fresh-token"),
+                MockResponse::json(200, r#"{"purchased":true}"#),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Purchased));
+        assert_eq!(session.get_csrf_token(), "fresh-token");
+        assert_eq!(server.requests()[1].headers["x-csrf-token"], "fresh-token");
+    }
+
+    #[tokio::test]
+    async fn not_for_sale_purchase_is_skipped_without_retrying() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(
+                200,
+                r#"{"purchased":false,"reason":"NotForSale","productId":1598473352}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::SaleEnded));
+        assert_eq!(session.retries.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn deleted_item_is_reported_as_not_found_without_retrying() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(
+                404,
+                r#"{"errors":[{"code":0,"message":"NotFound"}]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::NotFound));
+        assert_eq!(session.retries.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn moderated_item_missing_from_details_is_not_retried() {
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"errors":[{"code":0,"message":"Something went wrong"}]}"#,
+                )],
+            ),
+            (
+                "POST",
+                "/v1/catalog/items/details",
+                vec![MockResponse::json(200, r#"{"data":[]}"#)],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Moderated));
+        assert_eq!(session.retries.load(Ordering::Relaxed), 0);
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 1);
+    }
+
+    #[tokio::test]
+    async fn available_item_is_checked_for_moderation_once() {
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![
+                    MockResponse::json(
+                        200,
+                        r#"{"errors":[{"code":0,"message":"Something went wrong"}]}"#,
+                    ),
+                    MockResponse::json(
+                        200,
+                        r#"{"errors":[{"code":0,"message":"Something went wrong"}]}"#,
+                    ),
+                    MockResponse::json(200, r#"{"purchased":true}"#),
+                ],
+            ),
+            (
+                "POST",
+                "/v1/catalog/items/details",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"data":[{"id":14476435962,"itemType":"Asset","name":"Free Hat","productId":1598473352,"creatorType":"User","creatorTargetId":4372130,"price":0}]}"#,
+                )],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Purchased));
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 3);
+        assert_eq!(
+            server.count_requests("POST", "/v1/catalog/items/details"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn owned_item_is_attempted_without_owned_check_and_not_retried() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(
+                200,
+                r#"{"purchased":false,"reason":"AlreadyOwned","productId":1598473352}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &["--no-owned-check"]);
+        let asset = test_asset();
+
+        let availability = is_asset_available(&session, &asset).await.unwrap();
+        let status = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(availability == Availability::Available);
+        assert!(matches!(status, PurchaseStatus::AlreadyOwned));
+        assert_eq!(server.count_requests("GET", "/v1/users/"), 0);
+        assert_eq!(session.retries.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn two_step_verification_challenge_aborts_instead_of_retrying() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(
+                403,
+                r#"{"errors":[{"code":0,"message":"Challenge is required"}]}"#,
+            )
+            .with_header("rblx-challenge-type", "twostepverification")],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let error = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .err()
+            .unwrap();
+
+        assert!(error.is::<Aborted>());
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 1);
+    }
+
+    #[tokio::test]
+    async fn purchase_exceeding_max_spend_is_skipped() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(200, r#"{"purchased":true}"#)],
+        )])
+        .await;
+        let session = test_session(&server, &["--max-price", "10", "--max-spend", "15"]);
+        let mut asset = test_asset();
+        asset.price = Some(10);
+
+        let first = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+        let second = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(first, PurchaseStatus::Purchased));
+        assert!(matches!(second, PurchaseStatus::Skipped));
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 1);
+        assert_eq!(session.spent.load(Ordering::Relaxed), 10);
+    }
+
+    #[tokio::test]
+    async fn changed_price_is_read_again_and_purchase_retried() {
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![
+                    MockResponse::json(
+                        200,
+                        r#"{"purchased":false,"reason":"PriceChanged","productId":1598473352}"#,
+                    ),
+                    MockResponse::json(200, r#"{"purchased":true}"#),
+                ],
+            ),
+            (
+                "POST",
+                "/v1/catalog/items/details",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"data":[{"id":14476435962,"itemType":"Asset","name":"Free Hat","productId":1598473352,"creatorType":"User","creatorTargetId":4372130,"price":5}]}"#,
+                )],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &["--max-price", "10"]);
+        let mut asset = test_asset();
+        asset.price = Some(3);
+
+        let status = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Purchased));
+        let purchases: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .filter(|request| request.path.starts_with("/v1/purchases/products/"))
+            .map(|request| serde_json::from_str(&request.body).unwrap())
+            .collect();
+        assert_eq!(purchases.len(), 2);
+        assert_eq!(purchases[0]["expectedPrice"], 3);
+        assert_eq!(purchases[1]["expectedPrice"], 5);
+    }
+
+    #[tokio::test]
+    async fn price_is_confirmed_before_paid_purchases() {
+        let details = |price: u32| {
+            MockResponse::json(
+                200,
+                &format!(
+                    r#"{{"data":[{{"id":14476435962,"itemType":"Asset","name":"Free Hat","productId":1598473352,"creatorType":"User","creatorTargetId":4372130,"price":{}}}]}}"#,
+                    price
+                ),
+            )
+        };
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![MockResponse::json(200, r#"{"purchased":true}"#)],
+            ),
+            (
+                "POST",
+                "/v1/catalog/items/details",
+                vec![details(6), details(4)],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &["--max-price", "10", "--price-tolerance", "1"]);
+        let mut asset = test_asset();
+        asset.price = Some(3);
+
+        let jumped = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+        let within_tolerance = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(jumped, PurchaseStatus::Skipped));
+        assert!(matches!(within_tolerance, PurchaseStatus::Purchased));
+        let purchases: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .filter(|request| request.path.starts_with("/v1/purchases/products/"))
+            .map(|request| serde_json::from_str(&request.body).unwrap())
+            .collect();
+        assert_eq!(purchases.len(), 1);
+        assert_eq!(purchases[0]["expectedPrice"], 4);
+    }
+
+    #[tokio::test]
+    async fn changed_price_above_max_price_is_skipped() {
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"purchased":false,"reason":"PriceChanged","productId":1598473352}"#,
+                )],
+            ),
+            (
+                "POST",
+                "/v1/catalog/items/details",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"data":[{"id":14476435962,"itemType":"Asset","name":"Free Hat","productId":1598473352,"creatorType":"User","creatorTargetId":4372130,"price":25}]}"#,
+                )],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &["--max-price", "10"]);
+
+        let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Skipped));
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 1);
+    }
+
+    #[test]
+    fn creator_types_filter_items_by_creator_type() {
+        let args = Args::parse_from(["free-ugc-bulk-purchaser", "--creator-types", "group"]);
+        let group_asset = MarketplaceQueryResponseItem {
+            creator_type: "Group".to_string(),
+            ..test_asset()
+        };
+
+        assert!(is_creator_type_included(&args, &group_asset));
+        assert!(!is_creator_type_included(&args, &test_asset()));
+        assert!(is_creator_type_included(
+            &Args::parse_from(["free-ugc-bulk-purchaser"]),
+            &test_asset()
+        ));
+    }
+
+    #[tokio::test]
+    async fn own_creations_are_not_available() {
+        let server = MockServer::start(Vec::new()).await;
+        let session = test_session(&server, &[]);
+        let asset = MarketplaceQueryResponseItem {
+            creator_target_id: 1234,
+            ..test_asset()
+        };
+
+        let availability = is_asset_available(&session, &asset).await.unwrap();
+
+        assert!(availability == Availability::OwnCreation);
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_ownership_check_skips_the_item_unless_told_to_abort() {
+        let server = MockServer::start(vec![(
+            "GET",
+            "/v1/users/1234/items/",
+            vec![MockResponse::json(500, r#"{"errors":[{"code":0}]}"#)],
+        )])
+        .await;
+        let skipping = test_session(&server, &[]);
+        let aborting = test_session(&server, &["--on-owned-check-error", "abort"]);
+
+        let availability = is_asset_available(&skipping, &test_asset()).await.unwrap();
+
+        assert!(availability == Availability::Unknown);
+        assert!(is_asset_available(&aborting, &test_asset()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn batch_ownership_covers_the_page_and_missing_ids_are_checked_alone() {
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/v1/users/1234/items/is-owned",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"data":[
+                        {"itemType":"Asset","itemId":1,"isOwned":true},
+                        {"itemType":"Asset","itemId":2,"isOwned":false}
+                    ]}"#,
+                )],
+            ),
+            (
+                "GET",
+                "/v1/users/1234/items/Asset/3/is-owned",
+                vec![MockResponse::json(200, "true")],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &[]);
+        let assets: Vec<MarketplaceQueryResponseItem> = [1, 2, 3]
+            .into_iter()
+            .map(|id| MarketplaceQueryResponseItem { id, ..test_asset() })
+            .collect();
+
+        let owned = ownership::get_batch_ownership(&session, &assets)
+            .await
+            .unwrap();
+        assert_eq!(
+            owned,
+            HashMap::from([
+                (("Asset".to_string(), 1), true),
+                (("Asset".to_string(), 2), false)
+            ])
+        );
+
+        check_page_ownership(&session, &assets).await;
+        let mut availability = Vec::new();
+        for asset in assets.iter() {
+            availability.push(is_asset_available(&session, asset).await.unwrap());
+        }
+
+        assert!(
+            availability
+                == [
+                    Availability::Owned,
+                    Availability::Available,
+                    Availability::Owned
+                ]
+        );
+        assert_eq!(server.count_requests("GET", "/v1/users/"), 1);
+        assert_eq!(server.count_requests("POST", "/v1/users/"), 2);
+    }
+
+    #[tokio::test]
+    async fn batch_ownership_keeps_bundles_and_assets_of_the_same_id_apart() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/users/1234/items/is-owned",
+            vec![MockResponse::json(
+                200,
+                r#"{"data":[
+                    {"itemType":"Asset","itemId":7,"isOwned":true},
+                    {"itemType":"Bundle","itemId":7,"isOwned":false}
+                ]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+        let assets = vec![
+            MarketplaceQueryResponseItem {
+                id: 7,
+                ..test_asset()
+            },
+            MarketplaceQueryResponseItem {
+                id: 7,
+                item_type: "Bundle".to_string(),
+                ..test_asset()
+            },
+        ];
+
+        check_page_ownership(&session, &assets).await;
+
+        assert!(is_asset_available(&session, &assets[0]).await.unwrap() == Availability::Owned);
+        assert!(is_asset_available(&session, &assets[1]).await.unwrap() == Availability::Available);
+        assert_eq!(server.count_requests("GET", "/v1/users/"), 0);
+    }
+
+    #[tokio::test]
+    async fn csrf_token_falls_back_to_the_response_headers() {
+        let server = MockServer::start(vec![
+            (
+                "GET",
+                "/home",
+                vec![MockResponse::json(200, "<html><head></head></html>")],
+            ),
+            (
+                "POST",
+                "/v1/catalog/items/details",
+                vec![MockResponse::json(403, r#"{"errors":[{"code":0}]}"#)
+                    .with_header("x-csrf-token", "header-token")],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let csrf_token = get_csrf_token(&session.client, &session.args)
+            .await
+            .unwrap();
+
+        assert_eq!(csrf_token, "header-token");
+        assert_eq!(server.count_requests("GET", "/home"), 1);
+    }
+
+    #[tokio::test]
+    async fn owned_items_are_exported_across_inventory_pages() {
+        let server = MockServer::start(vec![(
+            "GET",
+            "/v2/users/1234/inventory",
+            vec![
+                MockResponse::json(
+                    200,
+                    r#"{"nextPageCursor":"page-2","data":[{"assetId":1,"assetName":"Hat"},{"assetId":2,"assetName":"Wings"}]}"#,
+                ),
+                MockResponse::json(
+                    200,
+                    r#"{"nextPageCursor":null,"data":[{"assetId":3,"assetName":"Bag"}]}"#,
+                ),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+        let path = std::env::temp_dir().join(format!("fugc-owned-{}.txt", std::process::id()));
+
+        let exported_items = export::export_owned(&session, &path).await.unwrap();
+
+        assert_eq!(exported_items, 3);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "1\tHat\n2\tWings\n3\tBag\n"
+        );
+        assert!(server.requests()[1].path.ends_with("cursor=page-2"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn collectible_is_purchased_through_the_v2_api() {
+        let server = MockServer::start(vec![
+            (
+                "POST",
+                "/marketplace-items/v1/items/details",
+                vec![MockResponse::json(
+                    200,
+                    r#"[{"collectibleItemId":"item-1","collectibleProductId":"product-1"}]"#,
+                )],
+            ),
+            (
+                "POST",
+                "/marketplace-sales/v2/purchase",
+                vec![MockResponse::json(200, r#"{"status":"Succeeded"}"#)],
+            ),
+        ])
+        .await;
+        let session = test_session(&server, &["--purchase-api", "v2"]);
+        let asset = MarketplaceQueryResponseItem {
+            collectible_item_id: Some("item-1".to_string()),
+            ..test_asset()
+        };
+
+        let status = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap()
+            .status;
+
+        assert!(matches!(status, PurchaseStatus::Purchased));
+        let purchase = server
+            .requests()
+            .into_iter()
+            .find(|request| request.path == "/marketplace-sales/v2/purchase")
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_str(&purchase.body).unwrap();
+        assert_eq!(body["collectibleProductId"], "product-1");
+        assert_eq!(body["expectedPrice"]["amount"], 0);
+        assert_eq!(body["purchaser"]["id"], "1234");
+        assert_eq!(body["seller"]["type"], "User");
+    }
+
+    #[tokio::test]
+    async fn purchase_routes_of_bundles_and_assets_of_the_same_id_are_kept_apart() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/marketplace-items/v1/items/details",
+            vec![
+                MockResponse::json(
+                    200,
+                    r#"[{"collectibleItemId":"asset-item","collectibleProductId":"asset-product"}]"#,
+                ),
+                MockResponse::json(
+                    200,
+                    r#"[{"collectibleItemId":"bundle-item","collectibleProductId":"bundle-product"}]"#,
+                ),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+        let asset = MarketplaceQueryResponseItem {
+            collectible_item_id: Some("asset-item".to_string()),
+            ..test_asset()
+        };
+        let bundle = MarketplaceQueryResponseItem {
+            item_type: "Bundle".to_string(),
+            collectible_item_id: Some("bundle-item".to_string()),
+            ..test_asset()
+        };
+
+        let mut product_ids = Vec::new();
+        for item in [&asset, &bundle, &asset] {
+            match resolve_purchase_route(&session, item).await.unwrap() {
+                PurchaseRoute::Collectible {
+                    collectible_product_id,
+                } => product_ids.push(collectible_product_id),
+                PurchaseRoute::Economy => panic!("{} wasn't routed as a collectible", item.id),
+            }
+        }
+
+        assert_eq!(
+            product_ids,
+            ["asset-product", "bundle-product", "asset-product"]
+        );
+        assert_eq!(
+            server.count_requests("POST", "/marketplace-items/v1/items/details"),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_ids_are_written_to_the_receipts_file() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![
+                MockResponse::json(200, r#"{"purchased":true,"transactionId":987654321}"#),
+                MockResponse::json(200, r#"{"purchased":true}"#),
+            ],
+        )])
+        .await;
+        let path = std::env::temp_dir().join(format!("fugc-receipts-{}.tsv", std::process::id()));
+        let mut session = test_session(&server, &[]);
+        session.receipts = Some(ReceiptsFile::create(&path).unwrap());
+
+        for _ in 0..2 {
+            let status = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+                .await
+                .unwrap()
+                .status;
+            assert!(matches!(status, PurchaseStatus::Purchased));
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents, "14476435962\t0\t987654321\n");
+    }
+
+    #[tokio::test]
+    async fn shown_events_filter_the_event_log() {
+        let server = MockServer::start(vec![]).await;
+        let path = std::env::temp_dir().join(format!("fugc-events-{}.jsonl", std::process::id()));
+        let mut session = test_session(&server, &["--show", "purchased,failed"]);
+        session.event_log = Some(EventLog::create(&path).unwrap());
+
+        session.log_event(&PurchaseEvent::Started { user_id: 1234 });
+        session.log_event(&PurchaseEvent::Skipped { id: 1, name: "a" });
+        session.log_event(&PurchaseEvent::Purchased {
+            id: 2,
+            name: "b",
+            price: 0,
+        });
+        session.log_event(&PurchaseEvent::Ratelimited { waited_ms: 0 });
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let events: Vec<String> = contents
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["event"].to_string()
+            })
+            .collect();
+        assert_eq!(events, vec!["\"started\"", "\"purchased\""]);
+        assert!(session.shows(EventKind::Failed));
+        assert!(!session.shows(EventKind::Skipped));
+    }
+
+    #[tokio::test]
+    async fn outcome_of_a_retried_purchase() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![
+                MockResponse::json(200, r#"{"errors":[{"code":0,"message":"InternalError"}]}"#),
+                MockResponse::json(200, r#"{"purchased":true}"#),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &["--max-price", "10"]);
+        let asset = MarketplaceQueryResponseItem {
+            price: Some(5),
+            ..test_asset()
+        };
+
+        let outcome = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome.status, PurchaseStatus::Purchased));
+        assert_eq!(outcome.item.id, 14476435962);
+        assert_eq!(outcome.item.name, "Free Hat");
+        assert_eq!(outcome.attempts, 2);
+        assert_eq!(outcome.spent, 5);
+        assert_eq!(outcome.error, None);
+    }
+
+    #[tokio::test]
+    async fn outcome_of_a_skipped_purchase() {
+        let server = MockServer::start(vec![]).await;
+        let session = test_session(&server, &["--max-price", "10", "--max-spend", "3"]);
+        let asset = MarketplaceQueryResponseItem {
+            price: Some(5),
+            ..test_asset()
+        };
+
+        let outcome = attempt_purchase(&session, &asset, Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome.status, PurchaseStatus::Skipped));
+        assert_eq!(outcome.attempts, 1);
+        assert_eq!(outcome.spent, 0);
+        assert_eq!(outcome.error, None);
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 0);
+    }
+
+    #[tokio::test]
+    async fn skipped_error_code_still_aborts_with_fail_fast() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(
+                200,
+                r#"{"errors":[{"code":16,"message":"Insufficient funds"}]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &["--skip-codes", "16", "--fail-fast"]);
+
+        let Err(error) =
+            attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO).await
+        else {
+            panic!("the purchase didn't abort");
+        };
+
+        assert!(error.is::<Aborted>());
+    }
+
+    #[tokio::test]
+    async fn outcome_of_a_failed_purchase() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(
+                200,
+                r#"{"errors":[{"code":16,"message":"Insufficient funds"}]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &["--skip-codes", "16"]);
+
+        let outcome = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome.status, PurchaseStatus::Failed));
+        assert_eq!(outcome.attempts, 1);
+        assert_eq!(outcome.spent, 0);
+        assert_eq!(
+            outcome.error,
+            Some(ApiErrorInfo {
+                code: Some(16),
+                message: "Insufficient funds".to_string(),
+            })
+        );
+        assert_eq!(
+            outcome.get_failure_reason(),
+            "Insufficient funds (error code 16)"
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_details_are_refused() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/catalog/items/details",
+            vec![MockResponse::json(
+                200,
+                r#"{"data":[{"id":14476435962,"itemType":"Asset","name":"Free Hat","productId":1598473352,"creatorType":"User","creatorTargetId":4372130,"price":0}]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &["--max-body-size", "64"]);
+
+        let Err(error) = get_current_details(&session, &test_asset()).await else {
+            panic!("an oversized body was read");
+        };
+
+        assert!(error
+            .to_string()
+            .contains("larger than the --max-body-size"));
+    }
+
+    #[tokio::test]
+    async fn captcha_challenge_aborts_instead_of_retrying() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/purchases/products/",
+            vec![MockResponse::json(
+                403,
+                r#"{"errors":[{"code":0,"message":"Challenge is required"}]}"#,
+            )
+            .with_header("rblx-challenge-type", "captcha")
+            .with_header("rblx-challenge-id", "5d3a8c1e")],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let error = attempt_purchase(&session, &test_asset(), Duration::ZERO, Duration::ZERO)
+            .await
+            .err()
+            .unwrap();
+
+        assert!(error.is::<Aborted>());
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 1);
+    }
+
+    #[test]
+    fn numeric_category_ids_use_id_parameters() {
+        let names =
+            Args::parse_from(["free-ugc-bulk-purchaser", "-c", "Accessories", "-s", "Hats"]);
+        let ids = Args::parse_from(["free-ugc-bulk-purchaser", "-c", "11", "-s", "19"]);
+        let mixed = Args::parse_from(["free-ugc-bulk-purchaser", "-c", "11", "-s", "Hats"]);
+
+        assert_eq!(
+            get_category_query(&names),
+            "category=Accessories&subcategory=Hats"
+        );
+        assert_eq!(get_category_query(&ids), "categoryId=11&subcategoryId=19");
+        assert!(validate_category(&mixed).is_err());
+    }
+
+    #[tokio::test]
+    async fn custom_headers_are_sent_with_requests() {
+        let server = MockServer::start(vec![(
+            "GET",
+            "/v1/users/authenticated",
+            vec![MockResponse::json(
+                200,
+                r#"{"id":1234,"name":"builderman","displayName":"builderman"}"#,
+            )],
+        )])
+        .await;
+        let args = Args::parse_from([
+            "free-ugc-bulk-purchaser",
+            "--header",
+            "Accept-Language: en-US",
+            "--users-base-url",
+            &server.base_url,
+        ]);
+
+        get_authenticated_user(&build_client(&args).unwrap(), &args)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            requests[0]
+                .headers
+                .get("accept-language")
+                .map(String::as_str),
+            Some("en-US")
+        );
+        assert!("Accept-Language".parse::<CustomHeader>().is_err());
+    }
+
+    #[tokio::test]
+    async fn locale_is_sent_with_search_requests() {
+        let server = MockServer::start(vec![(
+            "GET",
+            "/v2/search/items/details",
+            vec![MockResponse::json(
+                200,
+                r#"{"nextPageCursor":null,"data":[]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &["--locale", "EN-us"]);
+
+        get_search_page(&session.client, &session.args, &session.timings, &None)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            requests[0]
+                .headers
+                .get("accept-language")
+                .map(String::as_str),
+            Some("en-us")
+        );
+        assert!(parse_locale("en_US").is_err());
+    }
+
+    #[tokio::test]
+    async fn malformed_search_page_is_retried_with_a_smaller_page_size() {
+        let server = MockServer::start(vec![(
+            "GET",
+            "/v2/search/items/details",
+            vec![
+                MockResponse::json(200, r#"{"nextPageCursor":null,"data":[{"id":"#),
+                MockResponse::json(200, r#"{"nextPageCursor":null,"data":[]}"#),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+
+        let page = get_search_page(&session.client, &session.args, &session.timings, &None)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert!(page.data.unwrap().is_empty());
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].path.contains("limit=120"));
+        assert!(requests[1].path.contains("limit=60"));
+    }
+
+    #[tokio::test]
+    async fn gamepasses_for_sale_are_read_as_products_of_the_experience_creator() {
+        let server = MockServer::start(vec![
+            (
+                "GET",
+                "/v1/games/5555/game-passes",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"nextPageCursor":null,"data":[
+                        {"id":1,"name":"Free Pass","productId":11,"price":0},
+                        {"id":2,"name":"Offsale Pass","productId":null,"price":null},
+                        {"id":3,"name":"Paid Pass","productId":33,"price":50}
+                    ]}"#,
+                )],
+            ),
+            (
+                "GET",
+                "/v1/games?universeIds=5555",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"data":[{"id":5555,"creator":{"id":777,"type":"Group"}}]}"#,
+                )],
+            ),
+        ])
+        .await;
+        let session = test_session(
+            &server,
+            &["--source", "gamepasses", "--universe-id", "5555"],
+        );
+
+        let page = gamepasses::get_gamepasses_page(&session, 5555, &None)
+            .await
+            .unwrap();
+        let items = page.data.unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, 1);
+        assert_eq!(items[0].product_id, 11);
+        assert_eq!(items[0].item_type, "GamePass");
+        assert_eq!(items[0].creator_type, "Group");
+        assert_eq!(items[0].creator_target_id, 777);
+    }
+
+    #[test]
+    fn expiring_order_puts_the_closest_deadline_first() {
+        let mut assets: Vec<MarketplaceQueryResponseItem> = [
+            (1, "null"),
+            (2, r#""2030-01-02T00:00:00Z""#),
+            (3, r#""not a date""#),
+            (4, r#""2030-01-01T00:00:00.000Z""#),
+        ]
+        .iter()
+        .map(|(id, deadline)| {
+            serde_json::from_str(&format!(
+                r#"{{"id":{},"itemType":"Asset","name":"Hat","productId":1,"creatorType":"User","creatorTargetId":1,"offSaleDeadline":{}}}"#,
+                id, deadline
+            ))
+            .unwrap()
+        })
+        .collect();
+        let args = Args::parse_from(["free-ugc-bulk-purchaser", "--purchase-order", "expiring"]);
+
+        prioritize_assets(&args, &mut assets);
+
+        let ids: Vec<u64> = assets.iter().map(|asset| asset.id).collect();
+        assert_eq!(ids, vec![4, 2, 1, 3]);
+    }
+
+    #[test]
+    fn manifest_options_redact_credentials() {
+        let args = Args::parse_from([
+            "free-ugc-bulk-purchaser",
+            "--auth",
+            "secret-cookie",
+            "--header",
+            "Cookie: other-secret",
+            "--header",
+            "Accept-Language: en-US",
+            "--shard",
+            "2/4",
+        ]);
+
+        let options = serde_json::to_value(&args).unwrap();
+        let serialized = options.to_string();
+
+        assert_eq!(options["auth"], "[redacted]");
+        assert_eq!(options["csrf-token"], serde_json::Value::Null);
+        assert_eq!(options["shard"], "2/4");
+        assert_eq!(
+            options["headers"],
+            serde_json::json!(["cookie: [redacted]", "accept-language: en-US"])
+        );
+        assert!(!serialized.contains("secret-cookie"));
+        assert!(!serialized.contains("other-secret"));
+    }
+
+    #[test]
+    fn control_characters_are_removed_from_item_names() {
+        let item: MarketplaceQueryResponseItem = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "itemType": "Asset",
+                "name": "Hat \u001b]8;;https://example.com\u0007\u202eKrow 🎩\n",
+                "productId": 2,
+                "creatorType": "User",
+                "creatorTargetId": 3
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(item.name, "Hat ]8;;https://example.comKrow 🎩");
+    }
+
+    #[tokio::test]
+    async fn ids_file_is_read_a_page_at_a_time_skipping_malformed_lines() {
+        let server = MockServer::start(vec![(
+            "POST",
+            "/v1/catalog/items/details",
+            vec![MockResponse::json(
+                200,
+                r#"{"data":[
+                    {"id":1,"itemType":"Asset","name":"Free","productId":11,"creatorType":"User","creatorTargetId":5,"price":0},
+                    {"id":2,"itemType":"Asset","name":"Paid","productId":22,"creatorType":"User","creatorTargetId":5,"price":10}
+                ]}"#,
+            )],
+        )])
+        .await;
+        let session = test_session(&server, &[]);
+        let path = std::env::temp_dir().join(format!("fugc-ids-{}.txt", std::process::id()));
+        std::fs::write(&path, "# wishlist\n1\n\nnot-an-id\n 2 \n").unwrap();
+
+        let page = ids_file::get_ids_page(&session, &path, &None)
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let requests = server.requests();
+        let items = page.data.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, 1);
+        assert!(page.next_page_cursor.is_none());
+        assert_eq!(session.ignored_ids.load(Ordering::Relaxed), 1);
+        assert!(requests[0].body.contains(r#""id":2"#));
+    }
+
+    #[tokio::test]
+    async fn creator_store_keeps_the_free_items_of_a_group() {
+        let server = MockServer::start(vec![
+            (
+                "GET",
+                "/v1/search/items",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"nextPageCursor":"next","data":[
+                        {"id":1,"itemType":"Asset"},
+                        {"id":2,"itemType":"Bundle"},
+                        {"id":3,"itemType":"Asset"}
+                    ]}"#,
+                )],
+            ),
+            (
+                "POST",
+                "/v1/catalog/items/details",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"data":[
+                        {"id":1,"itemType":"Asset","name":"Free","productId":11,"creatorType":"Group","creatorTargetId":7,"price":0},
+                        {"id":2,"itemType":"Bundle","name":"Paid","productId":22,"creatorType":"Group","creatorTargetId":7,"price":10},
+                        {"id":3,"itemType":"Asset","name":"Off sale","productId":33,"creatorType":"Group","creatorTargetId":7}
+                    ]}"#,
+                )],
+            ),
+        ])
+        .await;
+        let session = test_session(
+            &server,
+            &["--creator-store", "7", "--creator-store-type", "group"],
+        );
+
+        let page = get_page(&session, &None).await.unwrap();
+
+        let requests = server.requests();
+        let items = page.data.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, 1);
+        assert_eq!(page.next_page_cursor.as_deref(), Some("next"));
+        assert!(requests[0]
+            .path
+            .contains("creatorTargetId=7&creatorType=Group"));
+        assert!(requests[1].body.contains(r#""itemType":"Bundle""#));
+    }
+
+    #[test]
+    fn asset_purchase_query_serializes_to_camel_case() {
+        let query = AssetPurchaseQuery {
+            expected_currency: 1,
+            expected_price: 0,
+            expected_seller_id: 123,
+            expected_purchaser_id: None,
+            expected_purchaser_type: None,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&query).unwrap(),
+            r#"{"expectedCurrency":1,"expectedPrice":0,"expectedSellerId":123}"#
+        );
+    }
+
+    #[test]
+    fn asset_purchase_query_body_matches_serialized_json() {
+        let body = Body::from(AssetPurchaseQuery {
+            expected_currency: 1,
+            expected_price: 0,
+            expected_seller_id: 4372130,
+            expected_purchaser_id: None,
+            expected_purchaser_type: None,
+        });
+
+        assert_eq!(
+            body.as_bytes().unwrap(),
+            br#"{"expectedCurrency":1,"expectedPrice":0,"expectedSellerId":4372130}"#
+        );
+    }
+
+    #[test]
+    fn asset_purchase_response_without_errors() {
+        let response: AssetPurchaseResponse = serde_json::from_str(
+            r#"{"purchased":true,"reason":"Success","productId":1590868838,"statusCode":500,"title":"Item Purchased","errorMsg":"","showDivId":"CompletedPurchase","shortfallPrice":0,"balanceAfterSale":0,"expectedPrice":0,"currency":1,"price":0,"assetId":0}"#,
+        )
+        .unwrap();
+
+        assert!(response.errors.is_none());
+    }
+
+    #[test]
+    fn asset_purchase_response_with_ratelimit_error() {
+        let response: AssetPurchaseResponse = serde_json::from_str(
+            r#"{"errors":[{"code":27,"message":"TooManyRequests","userFacingMessage":"Something went wrong"}]}"#,
+        )
+        .unwrap();
+
+        let errors = response.errors.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, RATELIMIT_ERROR_CODE);
+        assert_eq!(errors[0].message.as_deref(), Some("TooManyRequests"));
+    }
+
+    #[test]
+    fn marketplace_query_response_page() {
+        let response: MarketplaceQueryResponse = serde_json::from_str(
+            r#"{
+                "keyword": null,
+                "previousPageCursor": null,
+                "nextPageCursor": "2_1_a2b8d9b4c1",
+                "data": [
+                    {
+                        "id": 14476435962,
+                        "itemType": "Asset",
+                        "assetType": 8,
+                        "name": "Free Hat",
+                        "description": "",
+                        "productId": 1598473352,
+                        "itemStatus": [],
+                        "itemRestrictions": ["Collectible"],
+                        "creatorHasVerifiedBadge": false,
+                        "creatorType": "Group",
+                        "creatorTargetId": 33713879,
+                        "creatorName": "Some Group",
+                        "price": 0,
+                        "lowestPrice": 0,
+                        "priceStatus": "Free",
+                        "favoriteCount": 1520,
+                        "collectibleItemId": "8c2b0b2e-52b4-4c7e-a1f8-6b2c2b6c4d43"
+                    },
+                    {
+                        "id": 517,
+                        "itemType": "Bundle",
+                        "bundleType": 1,
+                        "name": "Classic Bundle",
+                        "productId": 31260,
+                        "creatorType": "User",
+                        "creatorTargetId": 1,
+                        "creatorName": "Roblox"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(response.next_page_cursor.as_deref(), Some("2_1_a2b8d9b4c1"));
+        assert!(response.errors.is_none());
+
+        let data = response.data.unwrap();
+        assert_eq!(data.len(), 2);
+
+        assert_eq!(data[0].id, 14476435962);
+        assert_eq!(data[0].item_type, "Asset");
+        assert_eq!(data[0].product_id, 1598473352);
+        assert_eq!(data[0].creator_type, "Group");
+        assert_eq!(data[0].creator_target_id, 33713879);
+        assert_eq!(data[0].price, Some(0));
+        assert_eq!(data[0].favorite_count, Some(1520));
+        assert_eq!(
+            data[0].collectible_item_id.as_deref(),
+            Some("8c2b0b2e-52b4-4c7e-a1f8-6b2c2b6c4d43")
+        );
+
+        assert_eq!(data[1].item_type, "Bundle");
+        assert_eq!(data[1].price, None);
+        assert_eq!(data[1].favorite_count, None);
+        assert_eq!(data[1].collectible_item_id, None);
+    }
+
+    #[test]
+    fn marketplace_query_response_with_errors() {
+        let response: MarketplaceQueryResponse =
+            serde_json::from_str(r#"{"errors":[{"code":0,"message":"Too many requests"}]}"#)
+                .unwrap();
+
+        assert!(response.data.is_none());
+        assert!(response.next_page_cursor.is_none());
+        assert_eq!(response.errors.unwrap()[0].code, 0);
+    }
+
+    #[tokio::test]
+    async fn reversed_pages_come_last_to_first() {
+        let page = |id: u64, next_page_cursor: &str| {
+            MockResponse::json(
+                200,
+                &format!(
+                    r#"{{"nextPageCursor":{},"data":[{{"id":{},"itemType":"Asset","name":"Item","productId":1,"creatorType":"User","creatorTargetId":1,"price":0}}]}}"#,
+                    next_page_cursor, id
+                ),
+            )
+        };
+        let server = MockServer::start(vec![(
+            "GET",
+            "/v2/search/items/details",
+            vec![
+                page(1, r#""2""#),
+                page(2, r#""3""#),
+                page(3, "null"),
+                page(1, r#""2""#),
+            ],
+        )])
+        .await;
+        let session = test_session(&server, &["--reverse", "--max-buffered-pages", "2"]);
+
+        let mut reversed_pages = ReversedPages::collect(&session, None).await.unwrap();
+        let mut ids = Vec::new();
+        while let Some(response) = reversed_pages.next(&session).await.unwrap() {
+            ids.extend(response.data.unwrap().iter().map(|item| item.id));
+        }
+
+        assert_eq!(ids, vec![3, 2, 1]);
+        assert_eq!(server.count_requests("GET", "/v2/search/items/details"), 4);
+        assert!(server.requests()[3].path.ends_with("&cursor="));
+    }
+
+    #[tokio::test]
+    async fn pending_account_warning_aborts_the_run() {
+        let server = MockServer::start(vec![
+            (
+                "GET",
+                "/v1/users/authenticated",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"id":1234,"name":"user","displayName":"user"}"#,
+                )],
+            ),
+            (
+                "GET",
+                "/v1/not-approved",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"punishmentId":1,"punishmentTypeDescription":"Warn","beginDate":"2026-10-01T00:00:00Z","endDate":null,"messageToUser":"Spam"}"#,
+                )],
+            ),
+        ])
+        .await;
+
+        let Err(error) = purchase_catalog(
+            Client::new(),
+            test_args(&server.base_url, &["--csrf-token", "csrf-token"]),
+            Arc::new(Timings::new(false)),
+            None,
+        )
+        .await
+        else {
+            panic!("The run went on despite the warning");
+        };
+
+        assert!(error.is::<Aborted>());
+        assert!(error.to_string().contains("Warn"));
+        assert!(error.to_string().contains("Spam"));
+        assert_eq!(server.count_requests("GET", "/v2/search/items/details"), 0);
+    }
+
+    #[tokio::test]
+    async fn search_stopping_early_goes_on_by_creator_type() {
+        let page = |ids: &[u64]| {
+            let items: Vec<String> = ids
+                .iter()
+                .map(|id| format!(r#"{{"id":{},"itemType":"Asset","name":"Item","productId":{},"creatorType":"User","creatorTargetId":4372130,"price":0}}"#, id, id))
+                .collect();
+            MockResponse::json(
+                200,
+                &format!(r#"{{"nextPageCursor":null,"data":[{}]}}"#, items.join(",")),
+            )
+        };
+        let server = MockServer::start(vec![
+            (
+                "GET",
+                "/v1/users/authenticated",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"id":1234,"name":"user","displayName":"user"}"#,
+                )],
+            ),
+            (
+                "GET",
+                "/v2/search/items/details",
+                vec![page(&[1, 2]), page(&[1, 3]), page(&[4])],
+            ),
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![MockResponse::json(200, r#"{"purchased":true}"#)],
+            ),
+        ])
+        .await;
+
+        let summary = purchase_catalog(
+            Client::new(),
+            test_args(
+                &server.base_url,
+                &[
+                    "--csrf-token",
+                    "csrf-token",
+                    "--no-owned-check",
+                    "--max-page-size",
+                    "2",
+                    "--min-page-size",
+                    "1",
+                    "--purchase-interval-ms",
+                    "0",
+                ],
+            ),
+            Arc::new(Timings::new(false)),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let searches: Vec<String> = server
+            .requests()
+            .into_iter()
+            .filter(|request| request.path.starts_with("/v2/search/items/details"))
+            .map(|request| request.path)
+            .collect();
+        assert_eq!(searches.len(), 3);
+        assert!(!searches[0].contains("creatorType"));
+        assert!(searches[1].contains("&creatorType=User&"));
+        assert!(searches[2].contains("&creatorType=Group&"));
+        assert_eq!(summary.purchased, 4);
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 4);
+    }
+
+    #[tokio::test]
+    async fn full_page_at_a_shrunk_size_still_splits_the_search() {
+        let page = |ids: &[u64]| {
+            let items: Vec<String> = ids
+                .iter()
+                .map(|id| format!(r#"{{"id":{},"itemType":"Asset","name":"Item","productId":{},"creatorType":"User","creatorTargetId":4372130,"price":0}}"#, id, id))
+                .collect();
+            MockResponse::json(
+                200,
+                &format!(r#"{{"nextPageCursor":null,"data":[{}]}}"#, items.join(",")),
+            )
+        };
+        let server = MockServer::start(vec![
+            (
+                "GET",
+                "/v1/users/authenticated",
+                vec![MockResponse::json(
+                    200,
+                    r#"{"id":1234,"name":"user","displayName":"user"}"#,
+                )],
+            ),
+            (
+                "GET",
+                "/v2/search/items/details",
+                vec![
+                    MockResponse::json(200, r#"{"nextPageCursor":null,"data":[{"id""#),
+                    page(&[1]),
+                    page(&[2]),
+                    page(&[3]),
+                ],
+            ),
+            (
+                "POST",
+                "/v1/purchases/products/",
+                vec![MockResponse::json(200, r#"{"purchased":true}"#)],
+            ),
+        ])
+        .await;
+
+        let summary = purchase_catalog(
+            Client::new(),
+            test_args(
+                &server.base_url,
+                &[
+                    "--csrf-token",
+                    "csrf-token",
+                    "--no-owned-check",
+                    "--max-page-size",
+                    "2",
+                    "--min-page-size",
+                    "1",
+                    "--purchase-interval-ms",
+                    "0",
+                ],
+            ),
+            Arc::new(Timings::new(false)),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let searches: Vec<String> = server
+            .requests()
+            .into_iter()
+            .filter(|request| request.path.starts_with("/v2/search/items/details"))
+            .map(|request| request.path)
+            .collect();
+        assert_eq!(searches.len(), 4);
+        assert!(searches[1].contains("&limit=1&"));
+        assert!(searches[2].contains("&creatorType=User&"));
+        assert!(searches[3].contains("&creatorType=Group&"));
+        assert_eq!(summary.purchased, 3);
+    }
+
+    async fn replay_cassette(name: &str, extra_args: &[&str]) -> (MockServer, RunSummary) {
+        let server = Cassette::load(name).replay().await;
+        let mut argv = vec![
+            "--csrf-token",
+            "csrf-token",
+            "--purchase-interval-ms",
+            "0",
+            "--ratelimit-interval-ms",
+            "0",
+        ];
+        argv.extend_from_slice(extra_args);
+
+        let summary = purchase_catalog(
+            Client::new(),
+            test_args(&server.base_url, &argv),
+            Arc::new(Timings::new(false)),
+            None,
+        )
+        .await
+        .unwrap();
+
+        (server, summary)
+    }
+
+    #[tokio::test]
+    async fn cassette_normal_page_purchases_every_item() {
+        let (server, summary) = replay_cassette("normal_page", &[]).await;
+
+        assert_eq!(summary.purchased, 2);
+        assert_eq!(summary.failed, 0);
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 2);
+    }
+
+    #[tokio::test]
+    async fn cassette_owned_item_is_not_purchased() {
+        let (server, summary) = replay_cassette("owned_item", &[]).await;
+
+        assert_eq!(summary.purchased, 0);
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 0);
+        assert_eq!(
+            server.count_requests("GET", "/v1/users/1234/items/Asset/14476512374/is-owned"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn cassette_ratelimit_is_waited_out_and_retried() {
+        let (server, summary) = replay_cassette("ratelimit", &[]).await;
+
+        assert_eq!(summary.purchased, 1);
+        assert_eq!(summary.failed, 0);
+        assert_eq!(summary.ratelimits, 1);
+        assert_eq!(server.count_requests("POST", "/v1/purchases/products/"), 2);
+    }
+
+    /// Records a cassette of a run over the ids of a file, through proxies
+    /// in front of the Roblox domains. Check the result for anything
+    /// identifying before adding it to tests/cassettes:
+    /// `FUGC_RECORD_AUTH=<cookie> FUGC_RECORD_IDS_FILE=<path> FUGC_RECORD_CASSETTE=<name> cargo test record_cassette -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn record_cassette() {
+        let auth = std::env::var("FUGC_RECORD_AUTH").expect("FUGC_RECORD_AUTH is not set");
+        let ids_file =
+            std::env::var("FUGC_RECORD_IDS_FILE").expect("FUGC_RECORD_IDS_FILE is not set");
+        let name = std::env::var("FUGC_RECORD_CASSETTE").expect("FUGC_RECORD_CASSETTE is not set");
+
+        let defaults = Args::parse_from(["free-ugc-bulk-purchaser"]);
+        let upstreams = [
+            &defaults.catalog_base_url,
+            &defaults.users_base_url,
+            &defaults.inventory_base_url,
+            &defaults.economy_base_url,
+            &defaults.apis_base_url,
+            &defaults.www_base_url,
+            &defaults.premium_features_base_url,
+            &defaults.groups_base_url,
+            &defaults.games_base_url,
+            &defaults.usermoderation_base_url,
+        ];
+
+        let recorder = Recorder::default();
+        let mut argv = vec![
+            "free-ugc-bulk-purchaser".to_string(),
+            "--auth".to_string(),
+            auth,
+            "--ids-file".to_string(),
+            ids_file,
+        ];
+        for (flag, upstream) in BASE_URL_FLAGS.iter().zip(upstreams) {
+            argv.push(flag.to_string());
+            argv.push(recorder.proxy(upstream).await);
+        }
+
+        let args = Args::parse_from(argv);
+        let client = build_client(&args).unwrap();
+        purchase_catalog(client, args, Arc::new(Timings::new(false)), None)
+            .await
+            .unwrap();
+
+        recorder.finish().save(&name);
+    }
 }
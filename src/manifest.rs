@@ -0,0 +1,44 @@
+use crate::{schedule::Timestamp, Args, RunSummary};
+use serde::{Serialize, Serializer};
+use std::{fs::File, io::BufWriter, path::Path, time::SystemTime};
+
+const REDACTED: &str = "[redacted]";
+
+/// Everything needed to reproduce a run: the resolved options after the
+/// config file was applied and what the run did with them
+#[derive(Serialize)]
+struct Manifest<'a> {
+    version: &'static str,
+    finished_at: Timestamp,
+    options: &'a Args,
+    summary: &'a RunSummary,
+}
+
+/// Keeps credentials out of the manifest while still showing they were set
+pub fn serialize_redacted<S: Serializer>(
+    value: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(|_| REDACTED).serialize(serializer)
+}
+
+pub fn write_manifest(
+    path: &Path,
+    args: &Args,
+    summary: &RunSummary,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::create(path)
+        .map_err(|error| format!("Failed to create manifest {}: {}", path.display(), error))?;
+
+    serde_json::to_writer_pretty(
+        BufWriter::new(file),
+        &Manifest {
+            version: env!("CARGO_PKG_VERSION"),
+            finished_at: Timestamp(SystemTime::now()),
+            options: args,
+            summary,
+        },
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use crate::Args;
+use colored::*;
+use reqwest::Client;
+use std::{error::Error, fmt, time::Duration};
+
+// Wait before trying to reach Roblox again, doubled after each attempt
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Roblox couldn't be reached at all, as opposed to answering with an error
+#[derive(Debug)]
+pub struct Unreachable(pub String);
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Couldn't reach Roblox ({}), check your network, proxy and DNS settings",
+            self.0
+        )
+    }
+}
+
+impl Error for Unreachable {}
+
+/// Whether the error, or any error it was caused by, is a failed DNS lookup,
+/// connection or timeout rather than a response
+pub fn is_connection_error(error: &(dyn Error + 'static)) -> bool {
+    let mut current = Some(error);
+
+    while let Some(error) = current {
+        if error.is::<Unreachable>() {
+            return true;
+        }
+
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            if error.is_connect() || error.is_timeout() {
+                return true;
+            }
+        }
+
+        current = error.source();
+    }
+
+    false
+}
+
+/// Makes sure the catalog can be reached before starting, trying again up
+/// to --network-retries times. Any response counts, only failing to connect
+/// does not
+pub async fn wait_for_connection(client: &Client, args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut delay = RECONNECT_DELAY;
+    let mut attempt = 0;
+
+    loop {
+        let error = match client.get(&args.catalog_base_url).send().await {
+            Ok(_) => return Ok(()),
+            Err(error) if is_connection_error(&error) => error,
+            Err(error) => return Err(error.into()),
+        };
+
+        if attempt >= args.network_retries {
+            return Err(Unreachable(error.to_string()).into());
+        }
+        attempt += 1;
+
+        println!(
+            "{} Couldn't reach Roblox, trying again in {} seconds ({} of {})",
+            "Warning".bold().yellow(),
+            delay.as_secs(),
+            attempt,
+            args.network_retries
+        );
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn refused_connection_is_reported_as_unreachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let args = Args::parse_from([
+            "free-ugc-bulk-purchaser",
+            "--network-retries",
+            "0",
+            "--catalog-base-url",
+            &base_url,
+        ]);
+        let error = wait_for_connection(&Client::new(), &args)
+            .await
+            .unwrap_err();
+
+        assert!(error.is::<Unreachable>());
+        assert!(is_connection_error(&*error));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{MarketplaceQueryResponseItem, PurchaseStatus};
+
+/// The item a purchase was for
+pub struct ItemSummary {
+    pub id: u64,
+    pub name: String,
+}
+
+impl From<&MarketplaceQueryResponseItem> for ItemSummary {
+    fn from(item: &MarketplaceQueryResponseItem) -> Self {
+        ItemSummary {
+            id: item.id,
+            name: item.name.clone(),
+        }
+    }
+}
+
+/// The last error a purchase ran into. Failures that never got an answer
+/// from the API, like timeouts, have no code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiErrorInfo {
+    pub code: Option<u32>,
+    pub message: String,
+}
+
+/// Everything known about a purchase once it settled, retries included
+pub struct PurchaseOutcome {
+    pub item: ItemSummary,
+    pub status: PurchaseStatus,
+    /// Requests made, 1 unless the purchase was retried
+    pub attempts: u32,
+    /// Robux spent, 0 unless it went through
+    pub spent: u32,
+    pub error: Option<ApiErrorInfo>,
+}
+
+impl PurchaseOutcome {
+    /// What went wrong, for the failures file
+    pub fn get_failure_reason(&self) -> String {
+        let reason = match &self.error {
+            Some(ApiErrorInfo {
+                code: Some(code),
+                message,
+            }) => format!("{} (error code {})", message, code),
+            Some(ApiErrorInfo {
+                code: None,
+                message,
+            }) => message.clone(),
+            None => "purchase failed".to_string(),
+        };
+
+        match self.attempts {
+            0 | 1 => reason,
+            attempts => format!("{} after {} attempts", reason, attempts),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static STDOUT_CLOSED: AtomicBool = AtomicBool::new(false);
+
+/// Writes a line to stdout for the crate's `println!`. Once stdout is
+/// closed, e.g. by piping into `head`, lines go to stderr instead of
+/// panicking, and the run stops at the next item
+pub fn print_line(line: fmt::Arguments) {
+    if !STDOUT_CLOSED.load(Ordering::Relaxed) {
+        match writeln!(io::stdout(), "{}", line) {
+            Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {
+                STDOUT_CLOSED.store(true, Ordering::Relaxed);
+                eprintln!("Output was closed, stopping the run");
+            }
+            _ => return,
+        }
+    }
+
+    eprintln!("{}", line);
+}
+
+pub fn is_stdout_closed() -> bool {
+    STDOUT_CLOSED.load(Ordering::Relaxed)
+}
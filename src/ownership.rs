@@ -0,0 +1,73 @@
+use crate::{get_cookie, MarketplaceQueryResponseItem, Session};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Instant};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OwnershipQueryItem<'a> {
+    item_type: &'a str,
+    item_id: u64,
+}
+
+#[derive(Serialize)]
+struct OwnershipQuery<'a> {
+    items: Vec<OwnershipQueryItem<'a>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OwnershipResponseItem {
+    item_type: String,
+    item_id: u64,
+    is_owned: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct OwnershipResponse {
+    data: Option<Vec<OwnershipResponseItem>>,
+}
+
+/// Checks the ownership of a whole page of items with one request, by item
+/// type and id since assets and bundles are numbered apart. Items the
+/// response leaves out or has no answer for are missing from the map and
+/// have to be checked one by one
+pub async fn get_batch_ownership(
+    session: &Session,
+    items: &[MarketplaceQueryResponseItem],
+) -> Result<HashMap<(String, u64), bool>, Box<dyn std::error::Error>> {
+    let started_at = Instant::now();
+    let response = session
+        .client
+        .post(format!(
+            "{}/v1/users/{}/items/is-owned",
+            session.args.inventory_base_url, session.user_id
+        ))
+        .json(&OwnershipQuery {
+            items: items
+                .iter()
+                .map(|item| OwnershipQueryItem {
+                    item_type: &item.item_type,
+                    item_id: item.id,
+                })
+                .collect(),
+        })
+        .header("Cookie", get_cookie(&session.args))
+        .header("X-CSRF-TOKEN", session.get_csrf_token())
+        .send()
+        .await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        return Err(format!("Batch ownership check failed with status {}", status).into());
+    }
+
+    let response = response.json::<OwnershipResponse>().await?;
+    session.timings.record("Ownership", started_at);
+
+    Ok(response
+        .data
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|item| Some(((item.item_type, item.item_id), item.is_owned?)))
+        .collect())
+}
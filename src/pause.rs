@@ -0,0 +1,70 @@
+use colored::*;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+    time::Duration,
+};
+
+// How often a paused run checks whether it was resumed
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static LISTEN: Once = Once::new();
+
+/// Toggles between paused and running on every SIGUSR1, e.g. from
+/// `kill -USR1 <pid>`. Only the first call starts listening, so runs over
+/// several accounts share the toggle
+pub fn listen_for_pause_signal() {
+    LISTEN.call_once(|| {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut signals = match signal(SignalKind::user_defined1()) {
+                Ok(signals) => signals,
+                Err(error) => {
+                    println!(
+                        "{} Failed to listen for SIGUSR1, pausing is unavailable: {}",
+                        "Warning".bold().yellow(),
+                        error
+                    );
+                    return;
+                }
+            };
+
+            println!(
+                "{}",
+                format!(
+                    "Send SIGUSR1 to pause or resume, e.g. kill -USR1 {}",
+                    std::process::id()
+                )
+                .truecolor(150, 150, 150)
+            );
+
+            tokio::spawn(async move {
+                while signals.recv().await.is_some() {
+                    let paused = !PAUSED.fetch_xor(true, Ordering::Relaxed);
+
+                    if paused {
+                        println!(
+                            "{}",
+                            "Pausing after the current purchase, send SIGUSR1 again to resume"
+                                .yellow()
+                        );
+                    } else {
+                        println!("{}", "Resumed".green());
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Waits at an item boundary for as long as the run is paused
+pub async fn wait_while_paused() {
+    while PAUSED.load(Ordering::Relaxed) {
+        tokio::time::sleep(PAUSE_POLL_INTERVAL).await;
+    }
+}
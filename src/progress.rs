@@ -0,0 +1,75 @@
+use colored::*;
+use std::time::{Duration, Instant};
+
+/// Tracks how many items were processed to report throughput and, when the
+/// search tells us how many items there are, an estimated time remaining
+pub struct Progress {
+    started_at: Instant,
+    processed_items: u64,
+    total_items: Option<u64>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Progress {
+            started_at: Instant::now(),
+            processed_items: 0,
+            total_items: None,
+        }
+    }
+
+    pub fn set_total_items(&mut self, total_items: Option<u64>) {
+        if self.total_items.is_none() {
+            self.total_items = total_items;
+        }
+    }
+
+    pub fn add_processed_items(&mut self, items: usize) {
+        self.processed_items += items as u64;
+    }
+
+    pub fn print(&self, page: u32) {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        let items_per_second = if elapsed > 0.0 {
+            self.processed_items as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let remaining = match self.total_items {
+            Some(total_items) if items_per_second > 0.0 => {
+                let remaining_items = total_items.saturating_sub(self.processed_items);
+                format!(
+                    "{} of {} items, about {} left",
+                    self.processed_items,
+                    total_items,
+                    format_duration(Duration::from_secs_f64(
+                        remaining_items as f64 / items_per_second
+                    ))
+                )
+            }
+            _ => format!("{} items, unknown total", self.processed_items),
+        };
+
+        println!(
+            "{}",
+            format!(
+                "Page {}: {} ({:.1} items/s)",
+                page, remaining, items_per_second
+            )
+            .truecolor(150, 150, 150)
+        );
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if seconds >= 3600 {
+        format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
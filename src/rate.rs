@@ -0,0 +1,106 @@
+use colored::*;
+use std::{
+    num::NonZeroU32,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    first_acquired_at: Option<Instant>,
+    acquired: u64,
+}
+
+/// Token bucket of --rate shared by every purchase attempt of a run, across
+/// accounts and retries. Holds a second's worth of purchases so short bursts
+/// pass while the rate over a minute stays within the budget
+pub struct RateLimiter {
+    per_minute: u32,
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Takes a non zero rate, at 0 the wait for a token would be infinite
+    pub fn new(per_minute: NonZeroU32) -> Self {
+        let per_minute = per_minute.get();
+        let capacity = (per_minute as f64 / 60.0).max(1.0);
+
+        RateLimiter {
+            per_minute,
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+                first_acquired_at: None,
+                acquired: 0,
+            }),
+        }
+    }
+
+    /// Time to wait before the next purchase is within the budget at `now`,
+    /// taking its token if it already is
+    fn try_acquire(&self, now: Instant) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let refill =
+            now.duration_since(bucket.refilled_at).as_secs_f64() * self.per_minute as f64 / 60.0;
+        bucket.tokens = (bucket.tokens + refill).min(self.capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.acquired += 1;
+            bucket.first_acquired_at.get_or_insert(now);
+            return None;
+        }
+
+        Some(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) * 60.0 / self.per_minute as f64,
+        ))
+    }
+
+    pub async fn acquire(&self) {
+        while let Some(wait) = self.try_acquire(Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Purchases per minute since the first one, `None` before a second went by
+    fn achieved_rate(&self, now: Instant) -> Option<f64> {
+        let bucket = self.bucket.lock().unwrap();
+        let elapsed = now.duration_since(bucket.first_acquired_at?).as_secs_f64();
+
+        (elapsed >= 1.0).then(|| bucket.acquired as f64 * 60.0 / elapsed)
+    }
+
+    pub fn print_summary(&self) {
+        if let Some(rate) = self.achieved_rate(Instant::now()) {
+            println!(
+                "  Purchased at {} per minute of the {} allowed by --rate",
+                format!("{:.1}", rate).bold().blue(),
+                self.per_minute
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purchases_past_the_burst_wait_for_a_token() {
+        let limiter = RateLimiter::new(NonZeroU32::new(120).unwrap());
+        let now = Instant::now();
+
+        assert_eq!(limiter.try_acquire(now), None);
+        assert_eq!(limiter.try_acquire(now), None);
+        assert_eq!(limiter.try_acquire(now), Some(Duration::from_millis(500)));
+        assert_eq!(limiter.try_acquire(now + Duration::from_millis(500)), None);
+        assert_eq!(
+            limiter.achieved_rate(now + Duration::from_secs(2)),
+            Some(90.0)
+        );
+    }
+}
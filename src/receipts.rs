@@ -0,0 +1,36 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+/// Appends the transaction id of each purchase to a file, one per line as
+/// the item id, the price paid and the transaction id separated by tabs
+pub struct ReceiptsFile {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl ReceiptsFile {
+    pub fn create(path: &Path) -> Result<ReceiptsFile, Box<dyn std::error::Error>> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| {
+                format!("Failed to open receipts file {}: {}", path.display(), error)
+            })?;
+
+        Ok(ReceiptsFile {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Written out right away so a crash can't lose a paid transaction
+    pub fn record(&self, id: u64, price: u32, transaction_id: &str) -> std::io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+
+        writeln!(writer, "{}\t{}\t{}", id, price, transaction_id)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{Args, RATELIMIT_ERROR_CODE};
+use colored::*;
+use std::fmt;
+
+/// How a failed purchase is handled, depending on the error code
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RetryPolicy {
+    /// Try the purchase again right away
+    Retry,
+    /// Give up on the item and move on to the next one
+    Skip,
+    /// Stop the whole run
+    Abort,
+    /// Wait out the ratelimit, then try again
+    Ratelimit,
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RetryPolicy::Retry => "retry",
+            RetryPolicy::Skip => "skip",
+            RetryPolicy::Abort => "abort",
+            RetryPolicy::Ratelimit => "ratelimit",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Error codes with a known policy. Codes not listed here are retried
+const RETRY_POLICIES: &[(u32, RetryPolicy)] = &[(RATELIMIT_ERROR_CODE, RetryPolicy::Ratelimit)];
+
+/// The codes given with --retry-codes, --skip-codes and --abort-codes
+fn get_overrides(args: &Args) -> impl Iterator<Item = (u32, RetryPolicy)> + '_ {
+    args.retry_codes
+        .iter()
+        .map(|&code| (code, RetryPolicy::Retry))
+        .chain(
+            args.skip_codes
+                .iter()
+                .map(|&code| (code, RetryPolicy::Skip)),
+        )
+        .chain(
+            args.abort_codes
+                .iter()
+                .map(|&code| (code, RetryPolicy::Abort)),
+        )
+}
+
+/// The policy of an error code, with the overrides of the command line
+/// taking precedence over the defaults
+pub fn get_retry_policy(args: &Args, code: u32) -> RetryPolicy {
+    get_overrides(args)
+        .chain(RETRY_POLICIES.iter().copied())
+        .find(|(policy_code, _)| *policy_code == code)
+        .map(|(_, policy)| policy)
+        .unwrap_or(RetryPolicy::Retry)
+}
+
+/// Refuses codes given more than one policy and prints the policies in
+/// effect when any were overridden
+pub fn validate_retry_codes(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let overrides: Vec<(u32, RetryPolicy)> = get_overrides(args).collect();
+
+    for (index, (code, policy)) in overrides.iter().enumerate() {
+        if let Some((_, other)) = overrides[..index]
+            .iter()
+            .find(|(other_code, other)| other_code == code && other != policy)
+        {
+            return Err(format!(
+                "Error code {} can't be handled as both {} and {}",
+                code, other, policy
+            )
+            .into());
+        }
+    }
+
+    if overrides.is_empty() {
+        return Ok(());
+    }
+
+    let mut policies: Vec<(u32, RetryPolicy)> = RETRY_POLICIES
+        .iter()
+        .map(|&(code, _)| code)
+        .chain(overrides.iter().map(|&(code, _)| code))
+        .map(|code| (code, get_retry_policy(args, code)))
+        .collect();
+    policies.sort_by_key(|&(code, _)| code);
+    policies.dedup();
+
+    println!(
+        "{}",
+        format!(
+            "Handling error codes as: {}, any other code: retry",
+            policies
+                .iter()
+                .map(|(code, policy)| format!("{} {}", code, policy))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+        .truecolor(150, 150, 150)
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn known_codes_use_their_policy_and_others_are_retried() {
+        let args = Args::parse_from(["free-ugc-bulk-purchaser"]);
+
+        assert_eq!(
+            get_retry_policy(&args, RATELIMIT_ERROR_CODE),
+            RetryPolicy::Ratelimit
+        );
+        assert_eq!(get_retry_policy(&args, 0), RetryPolicy::Retry);
+        assert_eq!(get_retry_policy(&args, 9999), RetryPolicy::Retry);
+    }
+
+    #[test]
+    fn overridden_codes_take_precedence_over_the_defaults() {
+        let args = Args::parse_from([
+            "free-ugc-bulk-purchaser",
+            "--skip-codes",
+            "5,6",
+            "--abort-codes",
+            &RATELIMIT_ERROR_CODE.to_string(),
+        ]);
+
+        assert_eq!(get_retry_policy(&args, 5), RetryPolicy::Skip);
+        assert_eq!(get_retry_policy(&args, 6), RetryPolicy::Skip);
+        assert_eq!(
+            get_retry_policy(&args, RATELIMIT_ERROR_CODE),
+            RetryPolicy::Abort
+        );
+        assert_eq!(get_retry_policy(&args, 0), RetryPolicy::Retry);
+        assert!(validate_retry_codes(&args).is_ok());
+
+        let conflicting = Args::parse_from([
+            "free-ugc-bulk-purchaser",
+            "--retry-codes",
+            "5",
+            "--skip-codes",
+            "5",
+        ]);
+        assert!(validate_retry_codes(&conflicting).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{get_page, MarketplaceQueryResponse, Session};
+use colored::*;
+use std::collections::VecDeque;
+
+/// Pages of a whole crawl handed out from the last to the first for
+/// --reverse. The cursor of every page is kept, but only the last
+/// --max-buffered-pages pages themselves, the earlier ones are read again
+/// when their turn comes
+pub struct ReversedPages {
+    cursors: Vec<Option<String>>,
+    pages: VecDeque<(usize, MarketplaceQueryResponse)>,
+    remaining: usize,
+}
+
+impl ReversedPages {
+    /// Reads every page starting at `next_page_cursor` up front
+    pub async fn collect(
+        session: &Session,
+        mut next_page_cursor: Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let max_buffered_pages = session.args.max_buffered_pages as usize;
+        let mut cursors = Vec::new();
+        let mut pages = VecDeque::new();
+
+        loop {
+            let response = get_page(session, &next_page_cursor).await?;
+            let cursor =
+                std::mem::replace(&mut next_page_cursor, response.next_page_cursor.clone());
+
+            if pages.len() == max_buffered_pages {
+                pages.pop_front();
+            }
+            pages.push_back((cursors.len(), response));
+            cursors.push(cursor);
+
+            println!(
+                "{}",
+                format!("Read {} pages to purchase in reverse", cursors.len())
+                    .truecolor(150, 150, 150)
+            );
+
+            if next_page_cursor.is_none() {
+                break;
+            }
+        }
+
+        if cursors.len() > max_buffered_pages {
+            println!(
+                "{} Only the last {} of {} pages are kept in memory, the others will be read again (see --max-buffered-pages)",
+                "Warning".bold().yellow(),
+                max_buffered_pages,
+                cursors.len()
+            );
+        }
+
+        Ok(ReversedPages {
+            remaining: cursors.len(),
+            cursors,
+            pages,
+        })
+    }
+
+    /// The next page going backwards, `None` once the first page was handed out
+    pub async fn next(
+        &mut self,
+        session: &Session,
+    ) -> Result<Option<MarketplaceQueryResponse>, Box<dyn std::error::Error>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        if self
+            .pages
+            .back()
+            .is_some_and(|(index, _)| *index == self.remaining)
+        {
+            return Ok(self.pages.pop_back().map(|(_, page)| page));
+        }
+
+        get_page(session, &self.cursors[self.remaining])
+            .await
+            .map(Some)
+    }
+}
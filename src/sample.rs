@@ -0,0 +1,58 @@
+use crate::seen::mix;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// Keeps each item with the probability of --sample-rate. Each item is
+/// decided from its id and the --seed alone, so a run with the same seed
+/// samples the same items whatever the pages hold or the order they come in
+pub struct Sampler {
+    rate: f64,
+    seed: u64,
+}
+
+impl Sampler {
+    pub fn new(rate: f64, seed: u64) -> Self {
+        Sampler { rate, seed }
+    }
+
+    /// A seed for runs without --seed, printed so the run can be repeated
+    pub fn random_seed() -> u64 {
+        RandomState::new().build_hasher().finish()
+    }
+
+    pub fn keep(&self, id: u64) -> bool {
+        // The top 53 bits fill the mantissa of a float in [0, 1)
+        ((mix(self.seed ^ id) >> 11) as f64 / (1u64 << 53) as f64) < self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_samples_the_same_items_at_about_the_rate() {
+        let sample = |seed, ids: &mut dyn Iterator<Item = u64>| {
+            let sampler = Sampler::new(0.25, seed);
+            let mut kept: Vec<u64> = ids.filter(|id| sampler.keep(*id)).collect();
+            kept.sort();
+            kept
+        };
+
+        let first = sample(42, &mut (0..10_000));
+
+        assert_eq!(first, sample(42, &mut (0..10_000).rev()));
+        assert_eq!(
+            sample(42, &mut (0..10_000).step_by(2)),
+            first
+                .iter()
+                .copied()
+                .filter(|id| id % 2 == 0)
+                .collect::<Vec<u64>>()
+        );
+        assert_ne!(first, sample(43, &mut (0..10_000)));
+        assert!((2_300..2_700).contains(&first.len()));
+    }
+}
@@ -0,0 +1,284 @@
+use crate::{get_search_page, progress::format_duration, Args, Timings};
+use colored::*;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::{self, IsTerminal, Write},
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// A point in time written as an RFC 3339 timestamp, e.g.
+/// `2024-05-01T18:00:00Z` or `2024-05-01T20:00:00+02:00`
+#[derive(Clone, Copy)]
+pub struct Timestamp(pub SystemTime);
+
+fn parse_number(value: &str, name: &str) -> Result<i64, String> {
+    if value.is_empty() || !value.chars().all(|character| character.is_ascii_digit()) {
+        return Err(format!("invalid {} {:?}", name, value));
+    }
+
+    value
+        .parse()
+        .map_err(|_| format!("invalid {} {:?}", name, value))
+}
+
+/// Days between 1970-01-01 and the given date of the proleptic Gregorian
+/// calendar, after Howard Hinnant's `days_from_civil`
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// The inverse of `days_from_civil`, after Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+
+    (
+        year_of_era + era * 400 + if month <= 2 { 1 } else { 0 },
+        month,
+        day,
+    )
+}
+
+/// Written in UTC, e.g. `2024-05-01T18:00:00Z`
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self
+            .0
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let time_of_day = seconds.rem_euclid(86400);
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            time_of_day / 3600,
+            time_of_day % 3600 / 60,
+            time_of_day % 60
+        )
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (date, time) = value
+            .split_once(['T', 't', ' '])
+            .ok_or("expected an RFC 3339 timestamp, e.g. 2024-05-01T18:00:00Z")?;
+
+        let mut date_parts = date.split('-');
+        let (Some(year), Some(month), Some(day), None) = (
+            date_parts.next(),
+            date_parts.next(),
+            date_parts.next(),
+            date_parts.next(),
+        ) else {
+            return Err(format!("invalid date {:?}", date));
+        };
+        let (year, month, day) = (
+            parse_number(year, "year")?,
+            parse_number(month, "month")?,
+            parse_number(day, "day")?,
+        );
+
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(format!("invalid date {:?}", date));
+        }
+
+        // The offset is `Z` or `±HH:MM` after the time of day
+        let (time, offset_seconds) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+            (time, 0)
+        } else {
+            let sign_position = time
+                .rfind(['+', '-'])
+                .ok_or("timestamp needs a time zone, e.g. Z or +02:00")?;
+            let (time, offset) = time.split_at(sign_position);
+            let (hours, minutes) = offset[1..]
+                .split_once(':')
+                .ok_or(format!("invalid time zone {:?}", offset))?;
+            let offset_seconds =
+                parse_number(hours, "time zone")? * 3600 + parse_number(minutes, "time zone")? * 60;
+
+            (
+                time,
+                if offset.starts_with('-') {
+                    -offset_seconds
+                } else {
+                    offset_seconds
+                },
+            )
+        };
+
+        // Fractions of a second are dropped
+        let time = time.split('.').next().unwrap_or_default();
+        let mut time_parts = time.split(':');
+        let (Some(hour), Some(minute), Some(second), None) = (
+            time_parts.next(),
+            time_parts.next(),
+            time_parts.next(),
+            time_parts.next(),
+        ) else {
+            return Err(format!("invalid time {:?}", time));
+        };
+        let (hour, minute, second) = (
+            parse_number(hour, "hour")?,
+            parse_number(minute, "minute")?,
+            parse_number(second, "second")?,
+        );
+
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(format!("invalid time {:?}", time));
+        }
+
+        let seconds =
+            days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second
+                - offset_seconds;
+
+        if seconds < 0 {
+            return Err("timestamps before 1970 aren't supported".to_string());
+        }
+
+        Ok(Timestamp(UNIX_EPOCH + Duration::from_secs(seconds as u64)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Counts down to `start_at`, checking every poll interval that the search
+/// answers so problems show up before the drop rather than at it
+pub async fn wait_until(
+    client: &Client,
+    args: &Args,
+    timings: &Timings,
+    start_at: Timestamp,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = io::stdout();
+    let is_terminal = stdout.is_terminal();
+    let poll_interval = Duration::from_secs(args.poll_interval);
+    let mut last_poll: Option<Instant> = None;
+
+    while let Ok(remaining) = start_at.0.duration_since(SystemTime::now()) {
+        if last_poll.is_none_or(|last_poll| last_poll.elapsed() >= poll_interval) {
+            last_poll = Some(Instant::now());
+
+            if is_terminal {
+                let _ = write!(stdout, "\r\x1b[2K");
+            }
+
+            match get_search_page(client, args, timings, &None).await {
+                Ok(page) => println!(
+                    "{}",
+                    format!(
+                        "Search is ready with {} items on the first page, starting in {}",
+                        page.data.unwrap_or_default().len(),
+                        format_duration(remaining)
+                    )
+                    .truecolor(150, 150, 150)
+                ),
+                Err(error) => println!("{} {}", "Search is not ready:".yellow(), error),
+            }
+        }
+
+        if is_terminal {
+            let _ = write!(
+                stdout,
+                "\r\x1b[2KStarting in {}..",
+                format_duration(remaining)
+            );
+            let _ = stdout.flush();
+        }
+
+        tokio::time::sleep(remaining.min(Duration::from_secs(1))).await;
+    }
+
+    if is_terminal {
+        let _ = write!(stdout, "\r\x1b[2K");
+        let _ = stdout.flush();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unix_seconds(timestamp: &str) -> u64 {
+        timestamp
+            .parse::<Timestamp>()
+            .unwrap()
+            .0
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn rfc3339_timestamps_are_parsed_with_their_offset() {
+        assert_eq!(unix_seconds("1970-01-01T00:00:00Z"), 0);
+        assert_eq!(unix_seconds("2024-05-01T18:00:00Z"), 1714586400);
+        assert_eq!(unix_seconds("2024-05-01T20:00:00.250+02:00"), 1714586400);
+        assert_eq!(unix_seconds("2024-02-29T13:30:00-04:30"), 1709229600);
+        assert!("2024-05-01T18:00:00".parse::<Timestamp>().is_err());
+        assert!("2024-13-01T18:00:00Z".parse::<Timestamp>().is_err());
+    }
+
+    #[test]
+    fn timestamps_are_written_in_utc() {
+        for timestamp in [
+            "1970-01-01T00:00:00Z",
+            "2000-02-29T23:59:59Z",
+            "2024-05-01T18:00:00Z",
+        ] {
+            assert_eq!(
+                timestamp.parse::<Timestamp>().unwrap().to_string(),
+                timestamp
+            );
+        }
+        assert_eq!(
+            "2024-05-01T20:00:00+02:00"
+                .parse::<Timestamp>()
+                .unwrap()
+                .to_string(),
+            "2024-05-01T18:00:00Z"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+/// splitmix64, used to derive the two base hashes of the bloom filter and
+/// to sample items
+pub fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9e3779b97f4a7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+/// A fixed size bloom filter over item ids. It never forgets an id but may
+/// claim to have seen one it hasn't, at roughly the configured rate once
+/// `capacity` ids were inserted
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    pub fn new(capacity: u64, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let capacity = capacity.max(1) as f64;
+        let bit_count = (-capacity * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let hash_count = ((bit_count as f64 / capacity) * ln2).round().max(1.0) as u32;
+
+        BloomFilter {
+            bits: vec![0; bit_count.div_ceil(64) as usize],
+            bit_count,
+            hash_count,
+        }
+    }
+
+    /// Returns whether the id may not have been inserted before
+    pub fn insert(&mut self, id: u64) -> bool {
+        let first = mix(id);
+        let second = mix(first) | 1;
+        let mut is_new = false;
+
+        for index in 0..self.hash_count as u64 {
+            let bit = first.wrapping_add(index.wrapping_mul(second)) % self.bit_count;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                is_new = true;
+            }
+        }
+
+        is_new
+    }
+}
+
+/// Items already handled in watch mode, exact by default or bounded in
+/// memory with --seen-bloom
+pub enum SeenItems {
+    Exact(HashSet<u64>),
+    Bloom(BloomFilter),
+}
+
+impl SeenItems {
+    /// Returns whether the id wasn't seen before
+    pub fn insert(&mut self, id: u64) -> bool {
+        match self {
+            SeenItems::Exact(ids) => ids.insert(id),
+            SeenItems::Bloom(filter) => filter.insert(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_filter_remembers_ids_with_few_false_positives() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+
+        for id in 0..10_000u64 {
+            filter.insert(id * 7919);
+        }
+        for id in 0..10_000u64 {
+            assert!(!filter.insert(id * 7919));
+        }
+
+        let false_positives = (1_000_000..1_001_000u64)
+            .filter(|id| !filter.insert(id * 7919))
+            .count();
+        assert!(false_positives < 30, "{} false positives", false_positives);
+    }
+}
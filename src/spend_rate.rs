@@ -0,0 +1,56 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Spends of the last minute for --max-spend-rate, a circuit breaker for
+/// runs that spend far faster than they should
+pub struct SpendWindow {
+    max_per_minute: u64,
+    spends: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+impl SpendWindow {
+    pub fn new(max_per_minute: u64) -> Self {
+        SpendWindow {
+            max_per_minute,
+            spends: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Adds a spend at `now`, returning the spend of the last minute when it
+    /// went over the limit
+    pub fn record(&self, price: u64, now: Instant) -> Option<u64> {
+        let mut spends = self.spends.lock().unwrap();
+
+        while spends
+            .front()
+            .is_some_and(|(spent_at, _)| now.duration_since(*spent_at) >= WINDOW)
+        {
+            spends.pop_front();
+        }
+        spends.push_back((now, price));
+
+        let spent: u64 = spends.iter().map(|(_, price)| price).sum();
+        (spent > self.max_per_minute).then_some(spent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_spends_of_the_last_minute_count() {
+        let window = SpendWindow::new(100);
+        let now = Instant::now();
+
+        assert_eq!(window.record(60, now), None);
+        assert_eq!(window.record(30, now + Duration::from_secs(30)), None);
+        assert_eq!(window.record(20, now + Duration::from_secs(50)), Some(110));
+        assert_eq!(window.record(10, now + Duration::from_secs(61)), None);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{Args, Source};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+const CHECKPOINT_EXTENSION: &str = "json";
+
+/// Where a crawl over one set of filters stopped, so --resume can pick it up
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub max_price: u32,
+    pub next_page_cursor: String,
+    pub page_index: u32,
+}
+
+impl Checkpoint {
+    pub fn new(args: &Args, next_page_cursor: String, page_index: u32) -> Self {
+        Checkpoint {
+            category: args.category.clone(),
+            subcategory: args.subcategory.clone(),
+            max_price: args.max_price,
+            next_page_cursor,
+            page_index,
+        }
+    }
+}
+
+/// FNV-1a, which unlike the std hashers is guaranteed to stay the same
+/// between builds so checkpoint names remain stable
+fn hash_filters(args: &Args) -> u64 {
+    let filters = format!(
+        "{}\0{}\0{}\0{:?}{}{}",
+        args.category.as_deref().unwrap_or_default(),
+        args.subcategory.as_deref().unwrap_or_default(),
+        args.max_price,
+        args.favorites,
+        if args.include_not_for_sale {
+            "\0not-for-sale"
+        } else {
+            ""
+        },
+        // Kept out of catalog runs so their existing checkpoints still match
+        match (
+            args.source,
+            args.universe_id,
+            &args.ids_file,
+            args.creator_store,
+        ) {
+            (Source::Gamepasses, Some(universe_id), _, _) => {
+                format!("\0gamepasses:{}", universe_id)
+            }
+            (_, _, Some(ids_file), _) => format!("\0ids:{}", ids_file.display()),
+            (_, _, _, Some(creator_id)) => format!(
+                "\0creator:{}:{}",
+                args.creator_store_type.get_api_name(),
+                creator_id
+            ),
+            _ => String::new(),
+        }
+    );
+
+    filters.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Each combination of search filters gets its own checkpoint, so runs over
+/// different categories can share a state directory
+pub fn get_checkpoint_path(args: &Args) -> PathBuf {
+    args.state_dir.join(format!(
+        "{:016x}.{}",
+        hash_filters(args),
+        CHECKPOINT_EXTENSION
+    ))
+}
+
+pub fn load_checkpoint(args: &Args) -> Result<Option<Checkpoint>, Box<dyn std::error::Error>> {
+    let path = get_checkpoint_path(args);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)?;
+    let checkpoint = serde_json::from_str(&contents)
+        .map_err(|error| format!("Failed to parse checkpoint {}: {}", path.display(), error))?;
+
+    Ok(Some(checkpoint))
+}
+
+pub fn save_checkpoint(
+    args: &Args,
+    checkpoint: &Checkpoint,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(&args.state_dir)?;
+
+    // Written aside and renamed so a crash never leaves half a checkpoint
+    let path = get_checkpoint_path(args);
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, serde_json::to_string(checkpoint)?)?;
+    fs::rename(&temporary_path, &path)?;
+
+    Ok(())
+}
+
+pub fn remove_checkpoint(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_checkpoint_path(args);
+
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+fn get_checkpoint_paths(state_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if !state_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = Vec::new();
+
+    for entry in fs::read_dir(state_dir)? {
+        let path = entry?.path();
+
+        if path
+            .extension()
+            .is_some_and(|extension| extension == CHECKPOINT_EXTENSION)
+        {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn get_age(path: &Path) -> Duration {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .unwrap_or_default()
+}
+
+pub fn list_checkpoints(state_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = get_checkpoint_paths(state_dir)?;
+
+    if paths.is_empty() {
+        println!("No checkpoints in {}", state_dir.display());
+        return Ok(());
+    }
+
+    for path in paths.iter() {
+        let checkpoint = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Checkpoint>(&contents).ok());
+
+        let Some(checkpoint) = checkpoint else {
+            println!("{} (unreadable)", path.display().to_string().red());
+            continue;
+        };
+
+        println!(
+            "{} category={} subcategory={} max-price={}, page {}, {} hours old",
+            path.display().to_string().bold(),
+            checkpoint.category.as_deref().unwrap_or("-"),
+            checkpoint.subcategory.as_deref().unwrap_or("-"),
+            checkpoint.max_price,
+            checkpoint.page_index,
+            get_age(path).as_secs() / 3600
+        );
+    }
+
+    Ok(())
+}
+
+/// Removes every checkpoint, or only those untouched for longer than
+/// `older_than`, returning how many were removed
+pub fn clear_checkpoints(
+    state_dir: &Path,
+    older_than: Option<Duration>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut removed = 0;
+
+    for path in get_checkpoint_paths(state_dir)? {
+        if older_than.is_none_or(|older_than| get_age(&path) > older_than) {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn checkpoints_are_kept_apart_per_category() {
+        let state_dir = std::env::temp_dir().join(format!("fugc-state-{}", std::process::id()));
+        let state_dir = state_dir.to_str().unwrap();
+        let hats = Args::parse_from([
+            "fugc",
+            "-c",
+            "Accessories",
+            "-s",
+            "Hats",
+            "--state-dir",
+            state_dir,
+        ]);
+        let faces = Args::parse_from([
+            "fugc",
+            "-c",
+            "Accessories",
+            "-s",
+            "Faces",
+            "--state-dir",
+            state_dir,
+        ]);
+
+        save_checkpoint(
+            &hats,
+            &Checkpoint::new(&hats, "2_1_a2b8d9b4c1".to_string(), 1),
+        )
+        .unwrap();
+
+        assert_ne!(get_checkpoint_path(&hats), get_checkpoint_path(&faces));
+        assert_eq!(
+            load_checkpoint(&hats).unwrap().unwrap().next_page_cursor,
+            "2_1_a2b8d9b4c1"
+        );
+        assert!(load_checkpoint(&faces).unwrap().is_none());
+
+        assert_eq!(clear_checkpoints(Path::new(state_dir), None).unwrap(), 1);
+        fs::remove_dir(state_dir).unwrap();
+    }
+
+    #[test]
+    fn checkpoints_are_kept_apart_per_creator_store() {
+        let catalog = Args::parse_from(["fugc"]);
+        let user_store = Args::parse_from(["fugc", "--creator-store", "7"]);
+        let group_store = Args::parse_from([
+            "fugc",
+            "--creator-store",
+            "7",
+            "--creator-store-type",
+            "group",
+        ]);
+        let other_store = Args::parse_from(["fugc", "--creator-store", "8"]);
+
+        let paths = [&catalog, &user_store, &group_store, &other_store].map(get_checkpoint_path);
+
+        for (index, path) in paths.iter().enumerate() {
+            assert!(!paths[index + 1..].contains(path));
+        }
+    }
+}
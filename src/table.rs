@@ -0,0 +1,138 @@
+use crate::{MarketplaceQueryResponseItem, PurchaseStatus, RunSummary};
+use colored::*;
+use std::{
+    collections::VecDeque,
+    io::{self, IsTerminal, Write},
+};
+
+// Number of recent purchases kept on screen
+const TABLE_ROWS: usize = 15;
+// Longer names are cut off to keep the columns aligned
+const NAME_WIDTH: usize = 40;
+
+struct Row {
+    id: u64,
+    name: String,
+    status: &'static str,
+    price: Option<u32>,
+}
+
+fn get_status_label(status: &PurchaseStatus) -> &'static str {
+    match status {
+        PurchaseStatus::Purchased => "purchased",
+        PurchaseStatus::Skipped => "skipped",
+        PurchaseStatus::SaleEnded => "sale ended",
+        PurchaseStatus::AlreadyOwned => "owned",
+        PurchaseStatus::NotFound => "not found",
+        PurchaseStatus::Moderated => "moderated",
+        PurchaseStatus::Ratelimited => "ratelimited",
+        PurchaseStatus::Failed => "failed",
+    }
+}
+
+fn truncate_name(name: &str) -> String {
+    if name.chars().count() > NAME_WIDTH {
+        format!(
+            "{}..",
+            name.chars().take(NAME_WIDTH - 2).collect::<String>()
+        )
+    } else {
+        name.to_string()
+    }
+}
+
+/// The last purchases and the running totals of --format table, redrawn in
+/// place on a terminal and written as plain lines otherwise
+pub struct LiveTable {
+    rows: VecDeque<Row>,
+    is_terminal: bool,
+}
+
+impl LiveTable {
+    pub fn new() -> Self {
+        LiveTable {
+            rows: VecDeque::with_capacity(TABLE_ROWS),
+            is_terminal: io::stdout().is_terminal(),
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        asset: &MarketplaceQueryResponseItem,
+        status: &PurchaseStatus,
+        summary: &RunSummary,
+        spent: u64,
+    ) {
+        let row = Row {
+            id: asset.id,
+            name: truncate_name(&asset.name),
+            status: get_status_label(status),
+            price: asset.price,
+        };
+
+        if !self.is_terminal {
+            println!(
+                "{}\t{}\t{}\t{}",
+                row.id,
+                row.status,
+                row.price.unwrap_or_default(),
+                row.name
+            );
+            return;
+        }
+
+        if self.rows.len() == TABLE_ROWS {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+        self.draw(summary, spent);
+    }
+
+    fn draw(&self, summary: &RunSummary, spent: u64) {
+        let mut stdout = io::stdout().lock();
+
+        // Clears the screen, so log lines printed since the last draw go too
+        let _ = write!(stdout, "\x1b[H\x1b[J");
+        let _ = writeln!(
+            stdout,
+            "{}",
+            format!(
+                "{:<12} {:<width$} {:<12} {:>6}",
+                "Id",
+                "Name",
+                "Status",
+                "Price",
+                width = NAME_WIDTH
+            )
+            .bold()
+        );
+
+        for row in self.rows.iter() {
+            let status = format!("{:<12}", row.status);
+            let _ = writeln!(
+                stdout,
+                "{:<12} {:<width$} {} {:>6}",
+                row.id,
+                row.name,
+                match row.status {
+                    "purchased" => status.green(),
+                    "failed" => status.red(),
+                    _ => status.truecolor(150, 150, 150),
+                },
+                row.price.map(|price| price.to_string()).unwrap_or_default(),
+                width = NAME_WIDTH
+            );
+        }
+
+        let _ = writeln!(
+            stdout,
+            "\nPurchased {}, skipped {}, already owned {}, failed {}, spent {} Robux",
+            summary.purchased.to_string().bold().blue(),
+            summary.skipped.to_string().bold().blue(),
+            summary.already_owned.to_string().bold().blue(),
+            summary.failed.to_string().bold().red(),
+            spent.to_string().bold().blue()
+        );
+        let _ = stdout.flush();
+    }
+}
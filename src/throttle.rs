@@ -0,0 +1,142 @@
+use std::{sync::Mutex, time::Duration};
+
+// Successful purchases needed in a row before the delay is lowered again
+const SUCCESS_STREAK: u32 = 5;
+const DECREASE_STEP: Duration = Duration::from_millis(250);
+
+struct ThrottleState {
+    delay: Duration,
+    success_streak: u32,
+}
+
+/// AIMD-style pacing of the delay between purchases: every throttled
+/// response doubles the delay and every streak of successes lowers it by a
+/// fixed step, never leaving the configured bounds
+pub struct AdaptiveThrottle {
+    min_delay: Duration,
+    max_delay: Duration,
+    state: Mutex<ThrottleState>,
+}
+
+impl AdaptiveThrottle {
+    pub fn new(min_delay: Duration, max_delay: Duration) -> Self {
+        AdaptiveThrottle {
+            min_delay,
+            max_delay: max_delay.max(min_delay),
+            state: Mutex::new(ThrottleState {
+                delay: min_delay,
+                success_streak: 0,
+            }),
+        }
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.state.lock().unwrap().delay
+    }
+
+    pub fn on_throttled(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        state.success_streak = 0;
+        state.delay = (state.delay * 2).clamp(self.min_delay, self.max_delay);
+        state.delay
+    }
+
+    pub fn on_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.success_streak += 1;
+
+        if state.success_streak >= SUCCESS_STREAK {
+            state.success_streak = 0;
+            state.delay = state
+                .delay
+                .saturating_sub(DECREASE_STEP)
+                .max(self.min_delay);
+        }
+    }
+}
+
+/// Delay after the `step`th purchase of a run warming up over `steps`
+/// purchases: starts at `start` and shrinks by the same factor each step
+/// until it reaches `interval`, which is used from then on
+pub fn warm_up_delay(interval: Duration, start: Duration, step: u32, steps: u32) -> Duration {
+    if step >= steps || start <= interval {
+        return interval;
+    }
+
+    let floor = interval.max(Duration::from_millis(1)).as_secs_f64();
+    let ratio = (floor / start.as_secs_f64()).powf(step as f64 / steps as f64);
+
+    start.mul_f64(ratio).max(interval)
+}
+
+// Each escalation of the ratelimit cooldown multiplies it by this much
+const COOLDOWN_ESCALATION_FACTOR: u32 = 4;
+
+/// Cooldown after the `streak`th ratelimit in a row: `base` until the streak
+/// passes `escalate_after`, then growing by a factor each time up to `max`
+pub fn escalated_cooldown(
+    base: Duration,
+    streak: u32,
+    escalate_after: u32,
+    max: Duration,
+) -> Duration {
+    let escalations = streak.saturating_sub(escalate_after);
+
+    if escalations == 0 {
+        return base;
+    }
+
+    let factor = COOLDOWN_ESCALATION_FACTOR.saturating_pow(escalations);
+    base.saturating_mul(factor).min(max.max(base))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_when_throttled_and_recovers_after_successes() {
+        let throttle =
+            AdaptiveThrottle::new(Duration::from_millis(1000), Duration::from_millis(3000));
+
+        assert_eq!(throttle.on_throttled(), Duration::from_millis(2000));
+        assert_eq!(throttle.on_throttled(), Duration::from_millis(3000));
+
+        for _ in 0..SUCCESS_STREAK {
+            throttle.on_success();
+        }
+        assert_eq!(throttle.delay(), Duration::from_millis(2750));
+
+        for _ in 0..SUCCESS_STREAK * 20 {
+            throttle.on_success();
+        }
+        assert_eq!(throttle.delay(), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn warm_up_ramps_down_to_the_interval() {
+        let interval = Duration::from_millis(1000);
+        let start = Duration::from_millis(8000);
+
+        assert_eq!(warm_up_delay(interval, start, 0, 3), start);
+        assert_eq!(warm_up_delay(interval, start, 1, 3).as_millis(), 4000);
+        assert_eq!(warm_up_delay(interval, start, 2, 3).as_millis(), 2000);
+        assert_eq!(warm_up_delay(interval, start, 3, 3), interval);
+        assert_eq!(warm_up_delay(interval, start, 0, 0), interval);
+    }
+
+    #[test]
+    fn cooldown_escalates_after_a_ratelimit_streak_up_to_the_cap() {
+        let base = Duration::from_secs(65);
+        let max = Duration::from_secs(900);
+
+        assert_eq!(escalated_cooldown(base, 1, 3, max), base);
+        assert_eq!(escalated_cooldown(base, 3, 3, max), base);
+        assert_eq!(
+            escalated_cooldown(base, 4, 3, max),
+            Duration::from_secs(260)
+        );
+        assert_eq!(escalated_cooldown(base, 5, 3, max), max);
+        assert_eq!(escalated_cooldown(base, 40, 3, max), max);
+    }
+}
@@ -0,0 +1,65 @@
+use colored::*;
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub struct Timings {
+    enabled: bool,
+    samples: Mutex<BTreeMap<&'static str, Vec<Duration>>>,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Self {
+        Timings {
+            enabled,
+            samples: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn record(&self, request: &'static str, started_at: Instant) {
+        if !self.enabled {
+            return;
+        }
+
+        let elapsed = started_at.elapsed();
+        println!(
+            "{}",
+            format!("{} request took {}ms", request, elapsed.as_millis()).truecolor(150, 150, 150)
+        );
+
+        self.samples
+            .lock()
+            .unwrap()
+            .entry(request)
+            .or_default()
+            .push(elapsed);
+    }
+
+    pub fn print_summary(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        for (request, samples) in self.samples.lock().unwrap().iter_mut() {
+            samples.sort();
+            println!(
+                "  {} requests: {}, p50 {}ms, p95 {}ms",
+                request,
+                samples.len().to_string().bold().blue(),
+                percentile(samples, 0.50).as_millis(),
+                percentile(samples, 0.95).as_millis()
+            );
+        }
+    }
+}
+
+fn percentile(sorted_samples: &[Duration], percentile: f64) -> Duration {
+    if sorted_samples.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = (percentile * sorted_samples.len() as f64).ceil() as usize;
+    sorted_samples[rank.clamp(1, sorted_samples.len()) - 1]
+}